@@ -0,0 +1,135 @@
+//! Procedural macros for the poseidonito library. These are re-exported by poseidonito, and
+//! should be used through it.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, ItemStruct, LitInt, LitStr};
+
+/// The arguments of the `poseidon_config` attribute.
+#[derive(Default)]
+struct ConfigArgs {
+    field: Option<LitStr>,
+    t: Option<LitInt>,
+    r_f: Option<LitInt>,
+    r_p: Option<LitInt>,
+    alpha: Option<LitInt>,
+    constants: Option<LitStr>,
+    matrix: Option<LitStr>,
+}
+
+impl ConfigArgs {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        let value = meta.value()?;
+        if meta.path.is_ident("field") {
+            self.field = Some(value.parse()?);
+        } else if meta.path.is_ident("t") {
+            self.t = Some(value.parse()?);
+        } else if meta.path.is_ident("r_f") {
+            self.r_f = Some(value.parse()?);
+        } else if meta.path.is_ident("r_p") {
+            self.r_p = Some(value.parse()?);
+        } else if meta.path.is_ident("alpha") {
+            self.alpha = Some(value.parse()?);
+        } else if meta.path.is_ident("constants") {
+            self.constants = Some(value.parse()?);
+        } else if meta.path.is_ident("matrix") {
+            self.matrix = Some(value.parse()?);
+        } else {
+            return Err(meta.error("unknown poseidon_config argument"));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the argument, or an error naming it if it is missing.
+fn required<T>(argument: Option<T>, name: &str) -> syn::Result<T> {
+    argument.ok_or_else(|| syn::Error::new(proc_macro2::Span::call_site(), format!("missing poseidon_config argument `{}`", name)))
+}
+
+/// Generates a `PoseidonConfig` implementation for a unit struct, whose parameters are
+/// loaded lazily from files.
+///
+/// ```ignore
+/// #[poseidon_config(field = "bn254", t = 3, r_f = 8, r_p = 57, constants = "constants.txt", matrix = "matrix.txt")]
+/// pub struct MyConfig;
+/// ```
+///
+/// The arguments are:
+///
+/// - `field`: the field of the configuration. Currently, only `"bn254"` (the BN254 scalar
+///   field) is supported.
+/// - `t`, `r_f`, `r_p`: the state width and the numbers of full and partial rounds.
+/// - `alpha`: the S-box exponent (optional, defaults to 5).
+/// - `constants`: the path of the round constants file, one hex-encoded element per line.
+/// - `matrix`: the path of the MDS matrix file, one row of `t` space-separated hex-encoded
+///   elements per line.
+///
+/// The paths are relative to the file applying the attribute, as for `include_str!`. The
+/// number of round constants is checked when they are first loaded.
+#[proc_macro_attribute]
+pub fn poseidon_config(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = ConfigArgs::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemStruct);
+    match expand(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(args: ConfigArgs, item: ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&item.generics, "poseidon_config does not support generic structs"));
+    }
+    let field_name = required(args.field, "field")?;
+    let field = match field_name.value().as_str() {
+        "bn254" => quote!(::poseidonito::__private::ark_bn254::Fr),
+        _ => return Err(syn::Error::new_spanned(&field_name, "unsupported field, expected \"bn254\"")),
+    };
+    let t = required(args.t, "t")?;
+    let r_f = required(args.r_f, "r_f")?;
+    if r_f.base10_parse::<usize>()? % 2 != 0 {
+        return Err(syn::Error::new_spanned(&r_f, "the number of full rounds must be even"));
+    }
+    let r_p = required(args.r_p, "r_p")?;
+    let alpha = match args.alpha {
+        Some(alpha) => quote!(#alpha),
+        None => quote!(5),
+    };
+    let constants = required(args.constants, "constants")?;
+    let matrix = required(args.matrix, "matrix")?;
+    let name = &item.ident;
+
+    Ok(quote! {
+        #item
+
+        const _: () = {
+            use ::poseidonito::__private::{Lazy, PoseidonConfig, num_round_constants, parse_constants, parse_matrix_rows};
+
+            static ROUND_CONSTANTS: Lazy<Vec<#field>> = Lazy::new(|| {
+                let constants = parse_constants(include_str!(#constants));
+                assert_eq!(constants.len(), num_round_constants(#t, #r_f, #r_p), "Wrong number of round constants in {}", #constants);
+                constants
+            });
+
+            static MDS_MATRIX: Lazy<[[#field; #t]; #t]> = Lazy::new(|| {
+                parse_matrix_rows(include_str!(#matrix))
+            });
+
+            impl PoseidonConfig<#field, #t> for #name {
+                const R_F: usize = #r_f;
+                const R_P: usize = #r_p;
+                const ALPHA: u64 = #alpha;
+
+                fn mds_matrix() -> &'static [[#field; #t]; #t] {
+                    &MDS_MATRIX
+                }
+
+                fn round_constants() -> &'static [#field] {
+                    &ROUND_CONSTANTS
+                }
+            }
+        };
+    })
+}
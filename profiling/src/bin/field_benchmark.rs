@@ -0,0 +1,32 @@
+//! Compares the throughput of the t = 3 Poseidon permutation over the BN254 and the
+//! BLS12-381 scalar fields. Both configurations use the `x^5` addition chain for the S-box.
+
+use std::hint::black_box;
+use std::time::Instant;
+use ark_ff::Field;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::ConfigX5_255_3;
+use poseidonito::PoseidonConfig;
+use poseidonito::perm;
+
+const ITERATIONS: u32 = 10000;
+
+/// Returns the average time in nanoseconds of one permutation.
+fn time_permutation<F: Field, P: PoseidonConfig<F, 3>>() -> f64 {
+    assert_eq!(P::ALPHA, 5, "Both configurations should use the x^5 S-box");
+    let mut state = [F::zero(), F::one(), F::one() + F::one()];
+    // Make sure that the constants are loaded before timing.
+    perm::<F, 3, P>(&mut state);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        perm::<F, 3, P>(black_box(&mut state));
+    }
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    let bn254 = time_permutation::<ark_bn254::Fr, ConfigX5_254_3>();
+    let bls12_381 = time_permutation::<ark_bls12_381::Fr, ConfigX5_255_3>();
+    println!("BN254 (x5_254_3):     {:.0} ns/permutation", bn254);
+    println!("BLS12-381 (x5_255_3): {:.0} ns/permutation", bls12_381);
+}
@@ -0,0 +1,30 @@
+//! Compares the latency of the first call to `x5_254_3::hash`, which parses the round
+//! constants and the MDS matrix from their hex strings into `Lazy` statics, with the latency
+//! of the following calls, which only compute the hash.
+//!
+//! The cold latency can only be measured once per process, so this should be run as a
+//! separate binary, and before anything else touches the constants.
+
+use std::hint::black_box;
+use std::time::Instant;
+use poseidonito::{field_vec_from_u64, x5_254_3};
+
+const ITERATIONS: u32 = 10000;
+
+fn main() {
+    let input = field_vec_from_u64(&[1, 2]);
+
+    let start = Instant::now();
+    black_box(x5_254_3::hash(black_box(&input)));
+    let cold = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(x5_254_3::hash(black_box(&input)));
+    }
+    let warm = start.elapsed().as_nanos() as f64 / ITERATIONS as f64;
+
+    println!("Cold (first call): {} ns", cold);
+    println!("Warm (average):    {:.0} ns", warm);
+    println!("Initialization:    {:.0} ns", cold as f64 - warm);
+}
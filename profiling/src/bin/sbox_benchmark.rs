@@ -0,0 +1,65 @@
+//! Compares the cost of the full rounds of the x5_254_3 permutation when the S-box is
+//! computed with the generic `pow` and with the `x^5` addition chain.
+
+use std::hint::black_box;
+use std::time::Instant;
+use ark_bn254::Fr;
+use ark_ff::Field;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::PoseidonConfig;
+use poseidonito::perm;
+
+const ITERATIONS: u32 = 10000;
+
+/// The full rounds of the x5_254_3 permutation, computing the S-box with the generic `pow`.
+struct FullRoundsGenericPow;
+impl PoseidonConfig<Fr, 3> for FullRoundsGenericPow {
+    const R_F: usize = ConfigX5_254_3::R_F;
+    const R_P: usize = 0;
+    const ALPHA: u64 = 5;
+
+    fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+        ConfigX5_254_3::mds_matrix()
+    }
+
+    fn round_constants() -> &'static [Fr] {
+        &ConfigX5_254_3::round_constants()[..3 * Self::R_F]
+    }
+
+    fn sbox(x: &Fr) -> Fr {
+        x.pow([Self::ALPHA])
+    }
+}
+
+/// The full rounds of the x5_254_3 permutation, computing the S-box with the addition chain.
+struct FullRoundsPow5;
+impl PoseidonConfig<Fr, 3> for FullRoundsPow5 {
+    const R_F: usize = ConfigX5_254_3::R_F;
+    const R_P: usize = 0;
+    const ALPHA: u64 = 5;
+
+    fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+        ConfigX5_254_3::mds_matrix()
+    }
+
+    fn round_constants() -> &'static [Fr] {
+        &ConfigX5_254_3::round_constants()[..3 * Self::R_F]
+    }
+}
+
+/// Returns the average time in nanoseconds of one full round of the permutation.
+fn time_full_round<P: PoseidonConfig<Fr, 3>>() -> f64 {
+    let mut state = [Fr::from(0), Fr::from(1), Fr::from(2)];
+    // Make sure that the constants are loaded before timing.
+    perm::<Fr, 3, P>(&mut state);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        perm::<Fr, 3, P>(black_box(&mut state));
+    }
+    start.elapsed().as_nanos() as f64 / (ITERATIONS as f64 * P::R_F as f64)
+}
+
+fn main() {
+    println!("Full round with generic pow: {:.1} ns", time_full_round::<FullRoundsGenericPow>());
+    println!("Full round with addition chain: {:.1} ns", time_full_round::<FullRoundsPow5>());
+}
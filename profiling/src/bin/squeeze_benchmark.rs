@@ -0,0 +1,32 @@
+//! Compares squeezing many elements one at a time with `squeeze_single`, which reads from a
+//! cached block, with squeezing them at once with `squeeze_n`, for a sponge of rate 2.
+
+use std::hint::black_box;
+use std::time::Instant;
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use poseidonito::sponge::Sponge;
+use poseidonito::x5_254_3::PermX5_254_3;
+
+const ITERATIONS: u32 = 1000;
+const ELEMENTS: usize = 64;
+
+type RateTwoSponge = Sponge<Fr, PermX5_254_3, 2, 3>;
+
+/// Returns the average time in nanoseconds of squeezing `ELEMENTS` elements.
+fn time_squeeze(squeeze: impl Fn(&mut RateTwoSponge) -> Fr) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let mut sponge = RateTwoSponge::new([Fr::zero(); 3]);
+        sponge.absorb_single(black_box(Fr::from(1)));
+        black_box(squeeze(&mut sponge));
+    }
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    let single = time_squeeze(|sponge| (0..ELEMENTS).map(|_| sponge.squeeze_single()).sum());
+    let n = time_squeeze(|sponge| sponge.squeeze_n(ELEMENTS).into_iter().sum());
+    println!("squeeze_single x {}: {:.0} ns", ELEMENTS, single);
+    println!("squeeze_n({}):       {:.0} ns", ELEMENTS, n);
+}
@@ -0,0 +1,39 @@
+//! Compares the MDS multiplication with the row-major matrix (`matrix_vector_mul`) and with
+//! the column-major matrix (`matrix_vector_mul_transposed`), for the t = 3 matrix of
+//! x5_254_3 and for a synthetic t = 12 matrix.
+
+use std::hint::black_box;
+use std::time::Instant;
+use ark_bn254::Fr;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::PoseidonConfig;
+use poseidonito::{matrix_vector_mul, matrix_vector_mul_transposed, transpose_matrix};
+
+const ITERATIONS: u32 = 100000;
+
+/// Returns the average time in nanoseconds of one multiplication.
+fn time_multiplication<const T: usize>(multiply: impl Fn(&[Fr; T]) -> [Fr; T]) -> f64 {
+    let mut state: [Fr; T] = std::array::from_fn(|i| Fr::from(i as u64 + 1));
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        state = multiply(black_box(&state));
+    }
+    black_box(state);
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    let matrix = ConfigX5_254_3::mds_matrix();
+    let transposed = ConfigX5_254_3::mds_matrix_transposed();
+    let row_major = time_multiplication(|state| matrix_vector_mul(matrix, state));
+    let column_major = time_multiplication(|state| matrix_vector_mul_transposed(transposed, state));
+    println!("t = 3,  row-major:    {:.0} ns/multiplication", row_major);
+    println!("t = 3,  column-major: {:.0} ns/multiplication", column_major);
+
+    let wide: [[Fr; 12]; 12] = std::array::from_fn(|i| std::array::from_fn(|j| Fr::from((i * 12 + j) as u64 + 1)));
+    let wide_transposed = transpose_matrix(&wide);
+    let row_major = time_multiplication(|state| matrix_vector_mul(&wide, state));
+    let column_major = time_multiplication(|state| matrix_vector_mul_transposed(&wide_transposed, state));
+    println!("t = 12, row-major:    {:.0} ns/multiplication", row_major);
+    println!("t = 12, column-major: {:.0} ns/multiplication", column_major);
+}
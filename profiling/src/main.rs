@@ -1,9 +1,8 @@
-
 use ark_bn254::Fr;
-use poseidonito::x5_254_3::hash;
+use poseidonito::x5_254_3::hash_iter;
 
 fn main() {
-    let input: Vec<Fr> = (1..100000).map(|x| Fr::from(x)).collect();
-    let output = hash(&input);
+    // The input is generated while it is absorbed, so it is never collected into a `Vec`.
+    let output = hash_iter((1..100000).map(Fr::from));
     println!("Hash output: {:?}", output);
-}
\ No newline at end of file
+}
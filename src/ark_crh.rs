@@ -0,0 +1,85 @@
+//! This module implements arkworks' collision-resistant hash traits with the x5_254_3 hash
+//! functions, so that they can be used with the Merkle trees of `ark-crypto-primitives`.
+//!
+//! `PoseidonCRH` hashes the leaves, which are slices of field elements, with
+//! `x5_254_3::hash`. `PoseidonTwoToOneCRH` compresses two nodes with `x5_254_3::compress_two`.
+//! Neither has any parameters, so `setup` returns `()`.
+
+use std::borrow::Borrow;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_crypto_primitives::Error;
+use ark_std::rand::Rng;
+
+use crate::x5_254_3;
+
+/// The x5_254_3 hash of a slice of field elements, as an arkworks `CRHScheme`.
+pub struct PoseidonCRH;
+
+impl CRHScheme for PoseidonCRH {
+    type Input = [Fr];
+    type Output = Fr;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_r: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(_parameters: &Self::Parameters, input: T) -> Result<Self::Output, Error> {
+        Ok(x5_254_3::hash(input.borrow())[0])
+    }
+}
+
+/// The x5_254_3 2-to-1 compression, as an arkworks `TwoToOneCRHScheme`.
+pub struct PoseidonTwoToOneCRH;
+
+impl TwoToOneCRHScheme for PoseidonTwoToOneCRH {
+    type Input = Fr;
+    type Output = Fr;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_r: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(_parameters: &Self::Parameters, left_input: T, right_input: T) -> Result<Self::Output, Error> {
+        Ok(x5_254_3::compress_two(*left_input.borrow(), *right_input.borrow()))
+    }
+
+    fn compress<T: Borrow<Self::Output>>(parameters: &Self::Parameters, left_input: T, right_input: T) -> Result<Self::Output, Error> {
+        Self::evaluate(parameters, left_input, right_input)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_crypto_primitives::merkle_tree::{Config, IdentityDigestConverter, MerkleTree};
+
+    struct PoseidonMerkleConfig;
+    impl Config for PoseidonMerkleConfig {
+        type Leaf = [Fr];
+        type LeafDigest = Fr;
+        type LeafInnerDigestConverter = IdentityDigestConverter<Fr>;
+        type InnerDigest = Fr;
+        type LeafHash = PoseidonCRH;
+        type TwoToOneHash = PoseidonTwoToOneCRH;
+    }
+
+    #[test]
+    fn test_merkle_tree() {
+        let leaves: Vec<Vec<Fr>> = (0..4u64).map(|i| vec![Fr::from(i), Fr::from(i + 1)]).collect();
+        let tree = MerkleTree::<PoseidonMerkleConfig>::new(&(), &(), &leaves).unwrap();
+
+        let digests: Vec<Fr> = leaves.iter().map(|leaf| x5_254_3::hash(leaf)[0]).collect();
+        let left = x5_254_3::compress_two(digests[0], digests[1]);
+        let right = x5_254_3::compress_two(digests[2], digests[3]);
+        assert_eq!(tree.root(), x5_254_3::compress_two(left, right));
+
+        let proof = tree.generate_proof(2).unwrap();
+        assert!(proof.verify(&(), &(), &tree.root(), leaves[2].as_slice()).unwrap());
+        assert!(!proof.verify(&(), &(), &tree.root(), leaves[3].as_slice()).unwrap());
+    }
+}
@@ -0,0 +1,115 @@
+//! This module provides hash functions for migrating from Pedersen hashes, with the same
+//! `hash_two` signature, following the conventions of circomlib's Poseidon (which most zk
+//! tooling, e.g. circom, snarkjs and Semaphore, is compatible with).
+//!
+//! The conventions are: the state has one element more than the number of inputs, the
+//! capacity is the first element and is initialized to zero, the inputs fill the rest of the
+//! state, a single permutation is applied, and the output is the first element of the state.
+//! The parameters are those of `ConfigX5_254_3` for two inputs and of `ConfigX5_254_4` for
+//! three inputs. `hash_circom_compatible` covers one to five inputs.
+//!
+//! Note the differences to Pedersen hashes:
+//!
+//! - The inputs and the output are field elements, instead of bit strings and curve points.
+//!   Inputs must therefore be reduced to field elements before hashing.
+//! - Poseidon is not additively homomorphic, so protocols relying on combining Pedersen
+//!   hashes of different inputs cannot be migrated this way.
+//! - The digests differ from the `x5_254_3::hash` sponge, which reads its rate from the
+//!   first element instead.
+
+use ark_bn254::Fr;
+use ark_ff::Zero;
+
+use crate::configurations::config_x5_254_2::ConfigX5_254_2;
+use crate::configurations::config_x5_254_4::ConfigX5_254_4;
+use crate::configurations::config_x5_254_5::ConfigX5_254_5;
+use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+use crate::configurations::poseidon_config::PoseidonConfig;
+use crate::permutation::perm;
+
+/// Hashes two elements, as circomlib's `Poseidon(2)`, i.e. the first element of the
+/// permutation of `[0, a, b]`. This differs from `x5_254_3::compress_two`, which permutes
+/// `[a, b, 0]`.
+pub fn hash_two(a: Fr, b: Fr) -> Fr {
+    crate::x5_254_3::hash_with_separator_byte(0, &[a, b])
+}
+
+/// Hashes three elements, as circomlib's `Poseidon(3)`.
+pub fn hash_three(a: Fr, b: Fr, c: Fr) -> Fr {
+    let mut state = [Fr::zero(), a, b, c];
+    perm::<Fr, 4, ConfigX5_254_4>(&mut state);
+    state[0]
+}
+
+/// Hashes `T - 1` elements with a single permutation of `[0, input..]`, as circomlib's
+/// `Poseidon(T - 1)`.
+fn hash_circom<const T: usize, C: PoseidonConfig<Fr, T>>(input: &[Fr]) -> Fr {
+    let mut state = [Fr::zero(); T];
+    state[1..].copy_from_slice(input);
+    perm::<Fr, T, C>(&mut state);
+    state[0]
+}
+
+/// Hashes one to five elements bit-compatibly with circomlib's `Poseidon(n)`, where `n` is the
+/// number of inputs.
+/// 
+/// circomlib has no variable-length hash: `Poseidon(n)` is a separate circuit for each arity,
+/// with the state width `t = n + 1` and the parameters (and number of partial rounds) of that
+/// width. The input length is thus encoded by the choice of the parameter set, while the
+/// capacity element `state[0]` is always initialized to zero (circomlib's default
+/// `initialState`). Hence, this dispatches on the input length to the config with `T = n + 1`.
+/// 
+/// Panics if the input is empty or has more than five elements, since circomlib does not
+/// define `Poseidon(0)`, and this library has no parameters for wider states.
+pub fn hash_circom_compatible(input: &[Fr]) -> Fr {
+    match input.len() {
+        1 => hash_circom::<2, ConfigX5_254_2>(input),
+        2 => hash_two(input[0], input[1]),
+        3 => hash_three(input[0], input[1], input[2]),
+        4 => hash_circom::<5, ConfigX5_254_5>(input),
+        5 => hash_circom::<6, ConfigX5_254_6>(input),
+        n => panic!("circomlib-compatible hashing supports 1 to 5 inputs, but got {}", n),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::MontFp;
+
+    #[test]
+    fn test_hash_two() {
+        let expected: Fr = MontFp!("7853200120776062878684798364095072458815029376092732009249414926327459813530");
+        assert_eq!(hash_two(Fr::from(1), Fr::from(2)), expected);
+        assert_ne!(hash_two(Fr::from(2), Fr::from(1)), expected);
+    }
+
+    #[test]
+    fn test_hash_three() {
+        let expected: Fr = MontFp!("6542985608222806190361240322586112750744169038454362455181422643027100751666");
+        assert_eq!(hash_three(Fr::from(1), Fr::from(2), Fr::from(3)), expected);
+        assert_ne!(hash_three(Fr::from(1), Fr::from(2), Fr::zero()), hash_two(Fr::from(1), Fr::from(2)));
+    }
+    #[test]
+    fn test_hash_circom_compatible() {
+        // The outputs of circomlibjs' `poseidon` for [1], [1, 2] and [1, 2, 3].
+        let expected: [Fr; 3] = [
+            MontFp!("18586133768512220936620570745912940619677854269274689475585506675881198879027"),
+            MontFp!("7853200120776062878684798364095072458815029376092732009249414926327459813530"),
+            MontFp!("6542985608222806190361240322586112750744169038454362455181422643027100751666"),
+        ];
+        for (n, expected) in (1..=3u64).zip(expected) {
+            let input: Vec<Fr> = (1..=n).map(Fr::from).collect();
+            assert_eq!(hash_circom_compatible(&input), expected);
+        }
+        // The arities are distinguished by their parameters, not by trailing zeroes.
+        assert_ne!(hash_circom_compatible(&[Fr::from(1), Fr::zero()]), hash_circom_compatible(&[Fr::from(1)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "supports 1 to 5 inputs")]
+    fn test_hash_circom_compatible_empty() {
+        hash_circom_compatible(&[]);
+    }
+}
@@ -1,35 +1,63 @@
-use ark_ff::{Field, PrimeField, BigInteger};
-use hex::{FromHex, encode};
+use std::collections::HashMap;
+use hex::FromHex;
+
+use crate::field_bytes::FieldBytes;
 
 
 /// Converts a hex string to a field element.
-fn hex_to_field<F: Field>(hex_str: &str) -> Result<F, String> {
+fn hex_to_field<F: FieldBytes>(hex_str: &str) -> Result<F, String> {
     if hex_str.trim().is_empty() {
         return Err("Empty hex string".to_string());
     }
-    let bytes = Vec::<u8>::from_hex(hex_str).expect("Invalid hex");
+    // Note that we really do want to panic here if the conversion fails,
+    // as this indicates that the provided hex strings, which should always be
+    // known at compile time, are not valid for the field F.
+    Ok(try_hex_to_field(hex_str).expect("Failed to convert hex to field element"))
+}
+
+/// Converts a hex string to a field element, returning an error if the string is not valid
+/// hex, or does not represent an element of the field `F`. This is meant for parameters which
+/// are only known at runtime.
+pub fn try_hex_to_field<F: FieldBytes>(hex_str: &str) -> Result<F, String> {
+    let bytes = Vec::<u8>::from_hex(hex_str.trim()).map_err(|e| format!("Invalid hex {}: {}", hex_str, e))?;
     let bytes = bytes
         .into_iter()
         .rev()
         .collect::<Vec<u8>>();
-    // Note that we really do want to panic here if the conversion fails,
-    // as this indicates that the provided hex strings, which should always be
-    // known at compile time, are not valid for the field F.
-    Ok(F::from_random_bytes(&bytes).expect("Failed to convert hex to field element"))
+    F::from_le_bytes_canonical(&bytes).ok_or_else(|| format!("{} is not a valid field element", hex_str))
+}
+
+/// Splits a parameter file into sections. A section starts with a line of the form `[name]`,
+/// and consists of all following non-empty lines up to the next section. Lines before the
+/// first section are ignored.
+pub fn parse_sections(raw: &str) -> HashMap<&str, Vec<&str>> {
+    let mut sections = HashMap::new();
+    let mut current: Option<&str> = None;
+    for line in raw.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            current = Some(name.trim());
+            sections.entry(name.trim()).or_insert_with(Vec::new);
+        } else if let Some(name) = current {
+            sections.entry(name).or_insert_with(Vec::new).push(line);
+        }
+    }
+    sections
 }
 
 /// Parses a string of hex-encoded field elements, one per line,
-/// into a vector of field elements.
-pub fn parse_constants<F: Field>(raw_constants: &str) -> Vec<F> {
+/// into a vector of field elements. Blank lines and comment lines starting with `#` (e.g.
+/// for noting the provenance of the constants) are skipped.
+pub fn parse_constants<F: FieldBytes>(raw_constants: &str) -> Vec<F> {
     raw_constants
         .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
         .filter_map(|line| hex_to_field(line).ok())
         .collect()
 }
 
 /// Parses a matrix represented as an array of strings, where each string has `T` space-separated
 /// hex-encoded field elements.
-pub fn parse_matrix<F: Field, const T: usize>(rows: [&str; T]) -> [[F; T]; T] {
+pub fn parse_matrix<F: FieldBytes, const T: usize>(rows: [&str; T]) -> [[F; T]; T] {
     let mut matrix = [[F::zero(); T]; T];
     for (i, row) in rows.iter().enumerate() {
         let elements: Vec<F> = row
@@ -44,15 +72,46 @@ pub fn parse_matrix<F: Field, const T: usize>(rows: [&str; T]) -> [[F; T]; T] {
     matrix
 }
 
+/// Parses a matrix file, where each non-empty line is a row of `T` space-separated
+/// hex-encoded field elements.
+pub fn parse_matrix_rows<F: FieldBytes, const T: usize>(raw_matrix: &str) -> [[F; T]; T] {
+    let rows: Vec<&str> = raw_matrix.lines().filter(|line| !line.trim().is_empty()).collect();
+    let rows: [&str; T] = rows.try_into().unwrap_or_else(|rows: Vec<&str>| {
+        panic!("The matrix has {} rows, expected {}", rows.len(), T)
+    });
+    parse_matrix(rows)
+}
+
+/// Parses a matrix given as a single whitespace-separated list of `T * T` hex-encoded field
+/// elements, as provided by some references, and reshapes it row-major, i.e. the first `T`
+/// elements form the first row. Panics if the number of elements is not exactly `T * T`.
+pub fn parse_matrix_flat<F: FieldBytes, const T: usize>(raw_matrix: &str) -> [[F; T]; T] {
+    let elements: Vec<F> = raw_matrix
+        .split_whitespace()
+        .filter_map(|s| hex_to_field(s).ok())
+        .collect();
+    if elements.len() != T * T {
+        panic!("The matrix has {} elements, expected {}", elements.len(), T * T);
+    }
+    let mut matrix = [[F::zero(); T]; T];
+    for (row, values) in matrix.iter_mut().zip(elements.chunks_exact(T)) {
+        row.copy_from_slice(values);
+    }
+    matrix
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ark_bn254::Fr;
+    use ark_ff::{PrimeField, BigInteger};
+    use hex::encode;
+    use crate::test_fields::F101;
 
     #[test]
     fn test_parse_constants() {
         let constants = "0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e\n00f1445235f2148c5986587169fc1bcd887b08d4d00868df5696fff40956e864";
-        let elements = parse_constants::<Fr>(&constants);
+        let elements = parse_constants::<Fr>(constants);
         assert_eq!(elements.len(), 2);
         // By testing against the sum given as a hex string, we can test that parsing is correct,
         // without bothering about the specifics of the field representation.
@@ -64,7 +123,7 @@ mod tests {
     #[test]
     fn test_leave_empty_rows() {
         let constants = "0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e\n\n00f1445235f2148c5986587169fc1bcd887b08d4d00868df5696fff40956e864\n\n\n";
-        let elements = parse_constants::<Fr>(&constants);
+        let elements = parse_constants::<Fr>(constants);
         assert_eq!(elements.len(), 2);
     
     }
@@ -76,6 +135,45 @@ mod tests {
         assert_eq!(elements.len(), 195);
     }
 
+    #[test]
+    fn test_try_hex_to_field() {
+        assert_eq!(try_hex_to_field::<Fr>("03"), Ok(Fr::from(3)));
+        assert!(try_hex_to_field::<Fr>("0g").is_err());
+        // The modulus itself is not a valid field element.
+        assert!(try_hex_to_field::<Fr>("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001").is_err());
+    }
+
+    #[test]
+    fn test_small_field() {
+        assert_eq!(try_hex_to_field::<F101>("00"), Ok(F101::from(0)));
+        assert_eq!(try_hex_to_field::<F101>("64"), Ok(F101::from(100)));
+        assert_eq!(try_hex_to_field::<F101>("0064"), Ok(F101::from(100)));
+        // The modulus, and values which do not fit in the 7 bits of the modulus.
+        assert!(try_hex_to_field::<F101>("65").is_err());
+        assert!(try_hex_to_field::<F101>("85").is_err());
+        assert!(try_hex_to_field::<F101>("ff").is_err());
+        // Values longer than the byte size of the field should not be truncated.
+        assert!(try_hex_to_field::<F101>("0102").is_err());
+        assert_eq!(parse_constants::<F101>("01\n64\n02"), vec![F101::from(1), F101::from(100), F101::from(2)]);
+        assert_eq!(parse_matrix_rows::<F101, 2>("01 02\n03 04"), [[F101::from(1), F101::from(2)], [F101::from(3), F101::from(4)]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_small_field_out_of_range_constant() {
+        parse_constants::<F101>("01\n0102");
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let raw = "ignored\n[first]\na b\n\nc\n[second]\n[third]\nd";
+        let sections = parse_sections(raw);
+        assert_eq!(sections["first"], vec!["a b", "c"]);
+        assert!(sections["second"].is_empty());
+        assert_eq!(sections["third"], vec!["d"]);
+        assert!(!sections.contains_key("ignored"));
+    }
+
     #[test]
     fn test_parse_matrix() {
         let matrix_str = [
@@ -90,4 +188,47 @@ mod tests {
         assert_eq!(matrix[1][1], hex_to_field::<Fr>("2e2419f9ec02ec394c9871c832963dc1b89d743c8c7b964029b2311687b1fe23").unwrap());
         assert_eq!(matrix[2][2], hex_to_field::<Fr>("19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0").unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_matrix_flat() {
+        let rows = [
+            "109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b 16ed41e13bb9c0c66ae119424fddbcbc9314dc9fdbdeea55d6c64543dc4903e0 2b90bba00fca0589f617e7dcbfe82e0df706ab640ceb247b791a93b74e36736d",
+            "2969f27eed31a480b9c36c764379dbca2cc8fdd1415c3dded62940bcde0bd771 2e2419f9ec02ec394c9871c832963dc1b89d743c8c7b964029b2311687b1fe23 101071f0032379b697315876690f053d148d4e109f5fb065c8aacc55a0f89bfa",
+            "143021ec686a3f330d5f9e654638065ce6cd79e28c5b3753326244ee65a1b1a7 176cc029695ad02582a70eff08a6fd99d057e12e58e7d7b6b16cdfabc8ee2911 19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0"
+        ];
+        let flat = rows.join(" ");
+        assert_eq!(parse_matrix_flat::<Fr, 3>(&flat), parse_matrix::<Fr, 3>(rows));
+        // Any whitespace, including newlines, separates the elements.
+        assert_eq!(parse_matrix_flat::<Fr, 2>("01\t02\n03  04"), [[Fr::from(1), Fr::from(2)], [Fr::from(3), Fr::from(4)]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_parse_matrix_flat_wrong_count() {
+        parse_matrix_flat::<Fr, 2>("01 02 03");
+    }
+
+    #[test]
+    fn test_skip_comment_lines() {
+        let raw_constants = "# Source: https://extgit.isec.tugraz.at/krypto/hadeshash\n01\n   # Round 1\n\n02\n#03\n04";
+        let constants = parse_constants::<Fr>(raw_constants);
+        assert_eq!(constants, vec![Fr::from(1), Fr::from(2), Fr::from(4)]);
+
+        let bundled = include_str!("config_x5_254_3/constants.txt");
+        assert_eq!(parse_constants::<Fr>(bundled).len(), 195);
+        let annotated = format!("# x5_254_3 round constants\n{}", bundled.replace('\n', "\n# next constant\n"));
+        assert_eq!(parse_constants::<Fr>(&annotated), parse_constants::<Fr>(bundled));
+    }
+
+    #[test]
+    fn test_parse_matrix_rows() {
+        let matrix = parse_matrix_rows::<Fr, 2>("01 02\n\n03 04\n");
+        assert_eq!(matrix, [[Fr::from(1), Fr::from(2)], [Fr::from(3), Fr::from(4)]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_parse_matrix_rows_wrong_size() {
+        parse_matrix_rows::<Fr, 2>("01 02\n03 04\n05 06");
+    }
+}
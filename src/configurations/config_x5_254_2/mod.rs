@@ -0,0 +1,29 @@
+//! The Poseidon configuration for the BN254 curve with 2 inputs and a 5-round sbox, as used by
+//! circomlib for hashing a single element. The parameters are generated with the Grain LFSR of
+//! the reference implementation at https://extgit.isec.tugraz.at/krypto/hadeshash.
+
+use ark_bn254::Fr;
+use once_cell::sync::Lazy;
+use crate::configurations::grain_lfsr::generate_parameters;
+use crate::configurations::poseidon_config::PoseidonConfig;
+
+/// The Poseidon configuration for the BN254 curve with 2 inputs and a 5-round sbox.
+pub struct ConfigX5_254_2;
+
+static PARAMETERS: Lazy<(Vec<Fr>, [[Fr; 2]; 2])> = Lazy::new(|| {
+    generate_parameters::<Fr, 2>(8, 56)
+});
+
+impl PoseidonConfig<Fr, 2> for ConfigX5_254_2 {
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+    const ALPHA: u64 = 5;
+
+    fn mds_matrix() -> &'static [[Fr; 2]; 2] {
+        &PARAMETERS.1
+    }
+
+    fn round_constants() -> &'static [Fr] {
+        &PARAMETERS.0
+    }
+}
@@ -1,11 +1,13 @@
 //! The Poseidon configuration for the BN254 curve with 3 inputs and a 5-round sbox.
 //! Obtained from the reference implementation at https://extgit.isec.tugraz.at/krypto/hadeshash
 
-use ark_ff::Field;
 use ark_bn254::Fr;
 use once_cell::sync::Lazy;
 use crate::configurations::poseidon_config::PoseidonConfig;
 use crate::configurations::config_creation_helper_functions::{parse_constants, parse_matrix};
+use crate::configurations::optimized_constants::{derive_optimized_constants, OptimizedConstants};
+
+pub mod static_config;
 
 /// The Poseidon configuration for the BN254 curve with 3 inputs and a 5-round sbox.
 pub struct ConfigX5_254_3;
@@ -22,10 +24,14 @@ static MDS_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
     ])
 });
 
+static OPTIMIZED_CONSTANTS: Lazy<OptimizedConstants<Fr, 3>> = Lazy::new(|| {
+    derive_optimized_constants::<Fr, 3, ConfigX5_254_3>().expect("x5_254_3 supports the optimized permutation")
+});
+
 impl PoseidonConfig<Fr, 3> for ConfigX5_254_3 {
     const R_F: usize = 8;
     const R_P: usize = 57;
-
+    const ALPHA: u64 = 5;
 
     fn mds_matrix() -> &'static [[Fr; 3]; 3] {
         &MDS_MATRIX
@@ -35,7 +41,7 @@ impl PoseidonConfig<Fr, 3> for ConfigX5_254_3 {
         &ROUND_CONSTANTS
     }
 
-    fn sbox(x: &Fr) -> Fr {
-        x.pow([5])
+    fn optimized_constants() -> Option<&'static OptimizedConstants<Fr, 3>> {
+        Some(&OPTIMIZED_CONSTANTS)
     }
 }
\ No newline at end of file
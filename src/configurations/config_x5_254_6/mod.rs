@@ -0,0 +1,29 @@
+//! The Poseidon configuration for the BN254 curve with 6 inputs and a 5-round sbox, as used by
+//! circomlib for hashing five elements. The parameters are generated with the Grain LFSR of
+//! the reference implementation at https://extgit.isec.tugraz.at/krypto/hadeshash.
+
+use ark_bn254::Fr;
+use once_cell::sync::Lazy;
+use crate::configurations::grain_lfsr::generate_parameters;
+use crate::configurations::poseidon_config::PoseidonConfig;
+
+/// The Poseidon configuration for the BN254 curve with 6 inputs and a 5-round sbox.
+pub struct ConfigX5_254_6;
+
+static PARAMETERS: Lazy<(Vec<Fr>, [[Fr; 6]; 6])> = Lazy::new(|| {
+    generate_parameters::<Fr, 6>(8, 60)
+});
+
+impl PoseidonConfig<Fr, 6> for ConfigX5_254_6 {
+    const R_F: usize = 8;
+    const R_P: usize = 60;
+    const ALPHA: u64 = 5;
+
+    fn mds_matrix() -> &'static [[Fr; 6]; 6] {
+        &PARAMETERS.1
+    }
+
+    fn round_constants() -> &'static [Fr] {
+        &PARAMETERS.0
+    }
+}
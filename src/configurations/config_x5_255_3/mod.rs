@@ -0,0 +1,33 @@
+//! The Poseidon configuration for the BLS12-381 scalar field with 3 inputs and a 5-round sbox.
+//! The parameters are generated with the Grain LFSR of the reference implementation at
+//! https://extgit.isec.tugraz.at/krypto/hadeshash, with the round numbers of the Poseidon
+//! paper for a 255-bit field (`R_F = 8`, `R_P = 57`). Note that, unlike the reference
+//! implementation, the generation does not check the MDS matrix against subspace trails.
+//!
+//! This configuration is only available with the `bls12_381` feature.
+
+use ark_bls12_381::Fr;
+use once_cell::sync::Lazy;
+use crate::configurations::grain_lfsr::generate_parameters;
+use crate::configurations::poseidon_config::PoseidonConfig;
+
+/// The Poseidon configuration for the BLS12-381 curve with 3 inputs and a 5-round sbox.
+pub struct ConfigX5_255_3;
+
+static PARAMETERS: Lazy<(Vec<Fr>, [[Fr; 3]; 3])> = Lazy::new(|| {
+    generate_parameters::<Fr, 3>(8, 57)
+});
+
+impl PoseidonConfig<Fr, 3> for ConfigX5_255_3 {
+    const R_F: usize = 8;
+    const R_P: usize = 57;
+    const ALPHA: u64 = 5;
+
+    fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+        &PARAMETERS.1
+    }
+
+    fn round_constants() -> &'static [Fr] {
+        &PARAMETERS.0
+    }
+}
@@ -0,0 +1,184 @@
+//! This module implements the Grain LFSR used by the Poseidon reference implementation
+//! (https://extgit.isec.tugraz.at/krypto/hadeshash) to generate the parameters of a
+//! Poseidon hash function.
+//!
+//! The LFSR has an 80-bit state, which is seeded with an encoding of the parameters:
+//!
+//! - 2 bits for the field type (1, since only prime fields are supported),
+//! - 4 bits for the S-box type (0, i.e. `x^ALPHA`),
+//! - 12 bits for the field size `n` in bits,
+//! - 12 bits for the state width `t`,
+//! - 10 bits for the number of full rounds `R_F`,
+//! - 10 bits for the number of partial rounds `R_P`,
+//! - 30 bits set to one.
+//!
+//! The first 160 output bits are discarded. After that, the output bits are taken in pairs,
+//! and if the first bit of a pair is one, the second bit is used, otherwise it is discarded.
+//!
+//! The round constants are drawn first, followed by the elements of the (Cauchy) MDS matrix.
+
+use ark_ff::{PrimeField, BigInteger};
+
+/// The Grain LFSR, in the self-shrinking mode used for Poseidon parameter generation.
+pub struct GrainLfsr {
+    state: [bool; 80],
+}
+
+impl GrainLfsr {
+
+    /// Creates a new LFSR seeded with the given Poseidon parameters, with the first 160 bits
+    /// discarded.
+    pub fn new(field_size: u32, t: usize, r_f: usize, r_p: usize) -> Self {
+        let mut state = [true; 80];
+        let mut position = 0;
+        for (value, width) in [(1, 2), (0, 4), (field_size as u64, 12), (t as u64, 12), (r_f as u64, 10), (r_p as u64, 10)] {
+            assert!(value < (1 << width), "Parameter {} does not fit in {} bits", value, width);
+            for i in (0..width).rev() {
+                state[position] = (value >> i) & 1 == 1;
+                position += 1;
+            }
+        }
+        let mut lfsr = GrainLfsr { state };
+        for _ in 0..160 {
+            lfsr.update();
+        }
+        lfsr
+    }
+
+    /// Shifts the LFSR one step and returns the new bit.
+    fn update(&mut self) -> bool {
+        let new_bit = self.state[62] ^ self.state[51] ^ self.state[38] ^ self.state[23] ^ self.state[13] ^ self.state[0];
+        self.state.rotate_left(1);
+        self.state[79] = new_bit;
+        new_bit
+    }
+
+    /// Returns the next output bit of the self-shrinking generator.
+    pub fn next_bit(&mut self) -> bool {
+        loop {
+            let first = self.update();
+            let second = self.update();
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// Returns the next `n` output bits, most significant bit first.
+    pub fn next_bits(&mut self, n: usize) -> Vec<bool> {
+        (0..n).map(|_| self.next_bit()).collect()
+    }
+
+    /// Returns the next field element, obtained by reading `F::MODULUS_BIT_SIZE` bits as a
+    /// big-endian integer, and rejecting integers which are not smaller than the modulus.
+    pub fn next_field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            let bits = self.next_bits(F::MODULUS_BIT_SIZE as usize);
+            if let Some(element) = F::from_bigint(F::BigInt::from_bits_be(&bits)) {
+                return element;
+            }
+        }
+    }
+}
+
+impl GrainLfsr {
+
+    /// Returns the next field element, obtained by reading `F::MODULUS_BIT_SIZE` bits as a
+    /// big-endian integer and reducing it modulo the modulus, as done by the reference
+    /// implementation for the MDS matrix.
+    fn next_field_element_mod_order<F: PrimeField>(&mut self) -> F {
+        let bits = self.next_bits(F::MODULUS_BIT_SIZE as usize);
+        F::from_be_bytes_mod_order(&F::BigInt::from_bits_be(&bits).to_bytes_be())
+    }
+
+    /// Returns the next Cauchy matrix `M[i][j] = 1 / (x_i + y_j)`, where the `x_i` and `y_j`
+    /// are `2T` distinct field elements, as in the reference implementation. Candidates with
+    /// repeated elements or a vanishing denominator are discarded.
+    /// 
+    /// Note that the reference implementation also discards matrices which fail its security
+    /// checks against infinitely long subspace trails, which are not performed here.
+    fn next_cauchy_matrix<F: PrimeField, const T: usize>(&mut self) -> [[F; T]; T] {
+        loop {
+            let mut elements: Vec<F> = (0..2 * T).map(|_| self.next_field_element_mod_order()).collect();
+            while (1..2 * T).any(|i| elements[..i].contains(&elements[i])) {
+                elements = (0..2 * T).map(|_| self.next_field_element_mod_order()).collect();
+            }
+            let (xs, ys) = elements.split_at(T);
+            if xs.iter().any(|x| ys.iter().any(|y| (*x + y).is_zero())) {
+                continue;
+            }
+            let mut matrix = [[F::zero(); T]; T];
+            for (row, x) in matrix.iter_mut().zip(xs.iter()) {
+                for (entry, y) in row.iter_mut().zip(ys.iter()) {
+                    *entry = (*x + y).inverse().expect("denominator is non-zero");
+                }
+            }
+            return matrix;
+        }
+    }
+}
+
+/// Generates the round constants (see `generate_round_constants`) and the MDS matrix for a
+/// Poseidon permutation over `F` with state size `T` and the S-box `x^ALPHA`, in the same
+/// way as the reference implementation. The MDS matrix is a Cauchy matrix, whose elements
+/// are drawn from the LFSR after the round constants (see `next_cauchy_matrix` for the
+/// security checks which are not performed).
+pub fn generate_parameters<F: PrimeField, const T: usize>(r_f: usize, r_p: usize) -> (Vec<F>, [[F; T]; T]) {
+    let mut lfsr = GrainLfsr::new(F::MODULUS_BIT_SIZE, T, r_f, r_p);
+    let round_constants = (0..T * (r_f + r_p)).map(|_| lfsr.next_field_element()).collect();
+    let mds_matrix = lfsr.next_cauchy_matrix();
+    (round_constants, mds_matrix)
+}
+
+/// Generates the `t * (R_F + R_P)` round constants for a Poseidon permutation over `F` with
+/// the S-box `x^ALPHA`, in the same order as the reference implementation.
+pub fn generate_round_constants<F: PrimeField>(t: usize, r_f: usize, r_p: usize) -> Vec<F> {
+    let mut lfsr = GrainLfsr::new(F::MODULUS_BIT_SIZE, t, r_f, r_p);
+    (0..t * (r_f + r_p)).map(|_| lfsr.next_field_element()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use crate::configurations::config_creation_helper_functions::parse_constants;
+
+    fn constant(hex: &str) -> Fr {
+        parse_constants::<Fr>(hex)[0]
+    }
+
+    #[test]
+    fn test_reproduce_x5_254_3_constants() {
+        let generated = generate_round_constants::<Fr>(3, 8, 57);
+        assert_eq!(generated.len(), 195);
+        assert_eq!(generated[0], constant("0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e"));
+        assert_eq!(generated[1], constant("00f1445235f2148c5986587169fc1bcd887b08d4d00868df5696fff40956e864"));
+        assert_eq!(generated[2], constant("08dff3487e8ac99e1f29a058d0fa80b930c728730b7ab36ce879f3890ecf73f5"));
+        assert_eq!(generated[96], constant("15742e99b9bfa323157ff8c586f5660eac6783476144cdcadf2874be45466b1a"));
+        assert_eq!(generated[192], constant("0fe0af7858e49859e2a54d6f1ad945b1316aa24bfbdd23ae40a6d0cb70c3eab1"));
+        assert_eq!(generated[193], constant("216f6717bbc7dedb08536a2220843f4e2da5f1daa9ebdefde8a5ea7344798d22"));
+        assert_eq!(generated[194], constant("1da55cc900f0d21f4a3e694391918a1b3c23b2ac773c6b3ef88e2e4228325161"));
+
+        let bundled = parse_constants::<Fr>(include_str!("config_x5_254_3/constants.txt"));
+        assert_eq!(generated, bundled);
+    }
+
+    #[test]
+    fn test_reproduce_x5_254_3_mds_matrix() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        use crate::configurations::poseidon_config::PoseidonConfig;
+        let (round_constants, mds_matrix) = generate_parameters::<Fr, 3>(8, 57);
+        assert_eq!(round_constants, generate_round_constants::<Fr>(3, 8, 57));
+        assert_eq!(mds_matrix, *ConfigX5_254_3::mds_matrix());
+    }
+
+    #[test]
+    fn test_seed_depends_on_parameters() {
+        let reference = generate_round_constants::<Fr>(3, 8, 57);
+        assert_ne!(generate_round_constants::<Fr>(3, 8, 56)[0], reference[0]);
+        assert_ne!(generate_round_constants::<Fr>(3, 10, 57)[0], reference[0]);
+        let wider = generate_round_constants::<Fr>(4, 8, 57);
+        assert_ne!(wider[0], reference[0]);
+    }
+}
@@ -1,3 +1,16 @@
 pub mod poseidon_config;
+pub mod config_x5_254_2;
 pub mod config_x5_254_3;
-pub(crate) mod config_creation_helper_functions;
\ No newline at end of file
+pub mod config_x5_254_4;
+pub mod config_x5_254_5;
+pub mod config_x5_254_6;
+#[cfg(feature = "bls12_381")]
+pub mod config_x5_255_3;
+pub mod grain_lfsr;
+pub mod optimized_constants;
+pub mod registry;
+pub mod runtime_config;
+pub(crate) mod config_creation_helper_functions;
+
+// Parsing a flat matrix is useful for importing matrices from other references at runtime.
+pub use config_creation_helper_functions::parse_matrix_flat;
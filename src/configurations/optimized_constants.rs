@@ -0,0 +1,319 @@
+//! This module defines the constants used by the optimized Poseidon permutation
+//! `perm_optimized`, in the layout used by Filecoin's Neptune library.
+//!
+//! In the optimized permutation, the round constants are folded through the linear layers
+//! ("compressed"), so that each partial round only adds a single constant, and the MDS
+//! multiplications of the partial rounds are replaced by multiplications with sparse matrices.
+//! The last full round before the partial rounds uses a special "pre-sparse" matrix.
+//!
+//! The layout is as follows:
+//!
+//! - `compressed_round_constants`: `T * R_F + R_P` constants, where the first `T` are added
+//!   before the first round, and the rest are added after the S-boxes of each round but the
+//!   last (`T` for a full round and one for a partial round).
+//! - `mds_matrix`: The MDS matrix, used for the full rounds.
+//! - `pre_sparse_matrix`: The matrix used for the last full round before the partial rounds.
+//! - `sparse_matrices`: One sparse matrix per partial round.
+
+use std::collections::HashMap;
+use ark_ff::{Field, PrimeField};
+
+use crate::configurations::config_creation_helper_functions::{parse_sections, try_hex_to_field};
+use crate::configurations::poseidon_config::PoseidonConfig;
+use crate::field_bytes::FieldBytes;
+
+/// A sparse matrix, which is the identity matrix except for its first row and first column.
+/// The first row is `w_hat`, and the rest of the first column is `v_rest`, i.e. the matrix
+/// maps a state `s` to `(sum_i w_hat[i] * s[i], s[1] + v_rest[0] * s[0], ...)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix<F: Field, const T: usize> {
+    pub w_hat: [F; T],
+    pub v_rest: Vec<F>,
+}
+
+impl<F: Field, const T: usize> SparseMatrix<F, T> {
+
+    /// Multiplies the matrix with the state, using `2T - 1` multiplications instead of `T^2`.
+    pub fn apply(&self, state: &[F; T]) -> [F; T] {
+        let mut result = *state;
+        result[0] = self.w_hat.iter().zip(state.iter()).map(|(w, s)| *w * s).sum();
+        for (word, v) in result.iter_mut().skip(1).zip(self.v_rest.iter()) {
+            *word += *v * state[0];
+        }
+        result
+    }
+}
+
+/// The constants of an optimized Poseidon permutation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimizedConstants<F: Field, const T: usize> {
+    pub r_f: usize,
+    pub r_p: usize,
+    pub alpha: u64,
+    pub compressed_round_constants: Vec<F>,
+    pub mds_matrix: [[F; T]; T],
+    pub pre_sparse_matrix: [[F; T]; T],
+    pub sparse_matrices: Vec<SparseMatrix<F, T>>,
+}
+
+/// The errors which can occur when importing optimized constants, or when parsing a combined
+/// parameter file with `parse_combined_params`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// A required section is missing.
+    MissingSection(String),
+    /// An element is not a valid number or field element.
+    InvalidElement(String),
+    /// A parameter is missing, unknown, given more than once, or out of range.
+    InvalidParameter(String),
+    /// A section (or a row of a matrix) does not have the expected number of elements.
+    WrongNumberOfElements { section: String, expected: usize, found: usize },
+}
+
+/// Returns the lines of a section, without the comment lines starting with `#`.
+pub(crate) fn section_lines<'a>(sections: &HashMap<&str, Vec<&'a str>>, name: &str) -> Result<Vec<&'a str>, ImportError> {
+    let lines = sections.get(name).ok_or_else(|| ImportError::MissingSection(name.to_string()))?;
+    Ok(lines.iter().copied().filter(|line| !line.starts_with('#')).collect())
+}
+
+/// Returns all elements of a section, i.e. all whitespace-separated tokens of its lines.
+pub(crate) fn section_tokens<'a>(sections: &HashMap<&str, Vec<&'a str>>, name: &str) -> Result<Vec<&'a str>, ImportError> {
+    Ok(section_lines(sections, name)?.into_iter().flat_map(str::split_whitespace).collect())
+}
+
+/// Parses a section holding a single integer.
+fn parse_count(sections: &HashMap<&str, Vec<&str>>, name: &str) -> Result<usize, ImportError> {
+    let tokens = section_tokens(sections, name)?;
+    if tokens.len() != 1 {
+        return Err(ImportError::WrongNumberOfElements { section: name.to_string(), expected: 1, found: tokens.len() });
+    }
+    tokens[0].parse().map_err(|_| ImportError::InvalidElement(tokens[0].to_string()))
+}
+
+/// Parses a section holding exactly `expected` hex-encoded field elements.
+fn parse_elements<F: FieldBytes>(sections: &HashMap<&str, Vec<&str>>, name: &str, expected: usize) -> Result<Vec<F>, ImportError> {
+    let tokens = section_tokens(sections, name)?;
+    if tokens.len() != expected {
+        return Err(ImportError::WrongNumberOfElements { section: name.to_string(), expected, found: tokens.len() });
+    }
+    tokens.iter().map(|token| try_hex_to_field(token).map_err(ImportError::InvalidElement)).collect()
+}
+
+/// Parses a section holding a `T x T` matrix in Neptune's convention, and transposes it.
+fn parse_transposed_matrix<F: FieldBytes, const T: usize>(sections: &HashMap<&str, Vec<&str>>, name: &str) -> Result<[[F; T]; T], ImportError> {
+    let elements = parse_elements::<F>(sections, name, T * T)?;
+    let mut matrix = [[F::zero(); T]; T];
+    for (index, element) in elements.into_iter().enumerate() {
+        matrix[index % T][index / T] = element;
+    }
+    Ok(matrix)
+}
+
+/// Imports optimized constants from a parameter file holding the fields of Neptune's
+/// `PoseidonConstants`. The file consists of the sections `[full_rounds]`, `[partial_rounds]`,
+/// `[compressed_round_constants]`, `[mds_matrix]`, `[pre_sparse_matrix]` and
+/// `[sparse_matrices]`, where the field elements are big-endian hex strings, and the matrices
+/// are written row by row. Each sparse matrix is written as `w_hat` followed by `v_rest`.
+///
+/// Note that Neptune multiplies the state as a row vector from the left with its matrices,
+/// while this library multiplies matrices with the state as a column vector. The dense
+/// matrices are therefore transposed on import. Since Neptune only supports the S-box `x^5`,
+/// the imported constants always have `alpha = 5`.
+pub fn import_neptune_constants<F: PrimeField + FieldBytes, const T: usize>(raw: &str) -> Result<OptimizedConstants<F, T>, ImportError> {
+    let sections = parse_sections(raw);
+    let r_f = parse_count(&sections, "full_rounds")?;
+    let r_p = parse_count(&sections, "partial_rounds")?;
+    if r_f < 2 || r_f % 2 != 0 {
+        return Err(ImportError::InvalidParameter(format!("full_rounds = {}", r_f)));
+    }
+    let compressed_round_constants = parse_elements(&sections, "compressed_round_constants", T * r_f + r_p)?;
+    let mds_matrix = parse_transposed_matrix(&sections, "mds_matrix")?;
+    let pre_sparse_matrix = parse_transposed_matrix(&sections, "pre_sparse_matrix")?;
+    let sparse_elements = parse_elements::<F>(&sections, "sparse_matrices", r_p * (2 * T - 1))?;
+    let sparse_matrices = sparse_elements
+        .chunks(2 * T - 1)
+        .map(|chunk| {
+            let mut w_hat = [F::zero(); T];
+            w_hat.copy_from_slice(&chunk[..T]);
+            SparseMatrix { w_hat, v_rest: chunk[T..].to_vec() }
+        })
+        .collect();
+    Ok(OptimizedConstants {
+        r_f,
+        r_p,
+        alpha: 5,
+        compressed_round_constants,
+        mds_matrix,
+        pre_sparse_matrix,
+        sparse_matrices,
+    })
+}
+
+/// Inverts a square matrix using Gaussian elimination, or returns `None` if it is singular.
+pub(crate) fn invert_matrix<F: Field>(matrix: &[Vec<F>]) -> Option<Vec<Vec<F>>> {
+    let n = matrix.len();
+    let mut left = matrix.to_vec();
+    let mut right: Vec<Vec<F>> = (0..n).map(|i| (0..n).map(|j| if i == j { F::one() } else { F::zero() }).collect()).collect();
+    for column in 0..n {
+        let pivot = (column..n).find(|row| !left[*row][column].is_zero())?;
+        left.swap(column, pivot);
+        right.swap(column, pivot);
+        let inverse = left[column][column].inverse()?;
+        for j in 0..n {
+            left[column][j] *= inverse;
+            right[column][j] *= inverse;
+        }
+        for row in 0..n {
+            if row != column && !left[row][column].is_zero() {
+                let factor = left[row][column];
+                for j in 0..n {
+                    let (l, r) = (left[column][j], right[column][j]);
+                    left[row][j] -= factor * l;
+                    right[row][j] -= factor * r;
+                }
+            }
+        }
+    }
+    Some(right)
+}
+
+/// Multiplies a square matrix with a vector, with both given as slices.
+fn multiply<F: Field>(matrix: &[Vec<F>], vector: &[F]) -> Vec<F> {
+    matrix.iter().map(|row| row.iter().zip(vector.iter()).map(|(m, v)| *m * v).sum()).collect()
+}
+
+/// Derives the optimized constants of a configuration, so that `perm_optimized` with these
+/// constants computes the same permutation as `perm` for the configuration.
+/// 
+/// The round constants are compressed by moving them through the MDS matrix (and, for the
+/// partial rounds, all but the first element of each constant are moved to the previous
+/// round, which is possible since the partial S-box only acts on the first element). The MDS
+/// matrix of each partial round is factored into a sparse matrix and a block-diagonal matrix
+/// acting only on the last `T - 1` elements, and the latter is moved to the previous round.
+/// 
+/// Returns `None` if the configuration is not supported by `perm_optimized`, i.e. if it has
+/// fewer than two full rounds, applies the partial S-box to another element than the first,
+/// uses the inverse S-box or an initial linear layer, adds round constants to only a part of
+/// the state or after the S-box, or if the MDS matrix (or a submatrix needed for the
+/// factorization) is singular. Note that a config overriding `sbox` with another function
+/// than `x^ALPHA` is not detected.
+pub fn derive_optimized_constants<F: Field, const T: usize, P: PoseidonConfig<F, T>>() -> Option<OptimizedConstants<F, T>> {
+    if P::R_F < 2 || P::PARTIAL_SBOX_INDEX != 0 || P::INVERSE_SBOX || P::HAS_INITIAL_MDS || P::ROUND_CONSTANT_WIDTH != T || !P::CONSTANTS_BEFORE_SBOX || T < 2 {
+        return None;
+    }
+    let half_full_rounds = P::R_F / 2;
+    let num_rounds = P::R_F + P::R_P;
+    let mds: Vec<Vec<F>> = P::mds_matrix().iter().map(|row| row.to_vec()).collect();
+    let mds_inverse = invert_matrix(&mds)?;
+
+    // post_constants[r] is added after the S-box of round r.
+    let round_constants: Vec<&[F]> = P::round_constants().chunks(T).collect();
+    let mut post_constants: Vec<Vec<F>> = (1..num_rounds).map(|round| multiply(&mds_inverse, round_constants[round])).collect();
+    for round in (half_full_rounds..half_full_rounds + P::R_P).rev() {
+        let mut rest = post_constants[round].clone();
+        rest[0] = F::zero();
+        post_constants[round].truncate(1);
+        for (constant, moved) in post_constants[round - 1].iter_mut().zip(multiply(&mds_inverse, &rest)) {
+            *constant += moved;
+        }
+    }
+    let mut compressed_round_constants = round_constants[0].to_vec();
+    for constants in &post_constants {
+        compressed_round_constants.extend_from_slice(constants);
+    }
+
+    // Factor the matrix of each partial round as S * B, where S is sparse and B is the
+    // identity on the first element, and move B to the previous round.
+    let mut matrix = mds.clone();
+    let mut sparse_matrices = Vec::with_capacity(P::R_P);
+    for _ in 0..P::R_P {
+        let block: Vec<Vec<F>> = matrix[1..].iter().map(|row| row[1..].to_vec()).collect();
+        let block_inverse = invert_matrix(&block)?;
+        let mut w_hat = [F::zero(); T];
+        w_hat[0] = matrix[0][0];
+        for (j, w) in w_hat.iter_mut().enumerate().skip(1) {
+            *w = (1..T).map(|k| matrix[0][k] * block_inverse[k - 1][j - 1]).sum();
+        }
+        let v_rest = matrix[1..].iter().map(|row| row[0]).collect();
+        sparse_matrices.push(SparseMatrix { w_hat, v_rest });
+        matrix = (0..T)
+            .map(|i| (0..T).map(|j| if i == 0 { mds[0][j] } else { (1..T).map(|k| block[i - 1][k - 1] * mds[k][j]).sum() }).collect())
+            .collect();
+    }
+    sparse_matrices.reverse();
+
+    let to_array = |matrix: &[Vec<F>]| {
+        let mut array = [[F::zero(); T]; T];
+        for (row, values) in array.iter_mut().zip(matrix.iter()) {
+            row.copy_from_slice(values);
+        }
+        array
+    };
+    Some(OptimizedConstants {
+        r_f: P::R_F,
+        r_p: P::R_P,
+        alpha: P::ALPHA,
+        compressed_round_constants,
+        mds_matrix: *P::mds_matrix(),
+        pre_sparse_matrix: to_array(&matrix),
+        sparse_matrices,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_sparse_matrix() {
+        let sparse = SparseMatrix::<Fr, 3> {
+            w_hat: [Fr::from(3), Fr::from(2), Fr::from(1)],
+            v_rest: vec![Fr::from(1), Fr::from(4)],
+        };
+        let state = [Fr::from(1), Fr::from(10), Fr::from(100)];
+        assert_eq!(sparse.apply(&state), [Fr::from(123), Fr::from(11), Fr::from(104)]);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let missing = "[full_rounds]\n2\n[partial_rounds]\n1";
+        assert_eq!(
+            import_neptune_constants::<Fr, 3>(missing),
+            Err(ImportError::MissingSection("compressed_round_constants".to_string()))
+        );
+        let too_few = "[full_rounds]\n2\n[partial_rounds]\n1\n[compressed_round_constants]\n01 02";
+        assert_eq!(
+            import_neptune_constants::<Fr, 3>(too_few),
+            Err(ImportError::WrongNumberOfElements { section: "compressed_round_constants".to_string(), expected: 7, found: 2 })
+        );
+        let odd_rounds = "[full_rounds]\n3\n[partial_rounds]\n1";
+        assert!(matches!(import_neptune_constants::<Fr, 3>(odd_rounds), Err(ImportError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_invert_matrix() {
+        let matrix = vec![vec![Fr::from(2), Fr::from(1)], vec![Fr::from(1), Fr::from(1)]];
+        let inverse = invert_matrix(&matrix).unwrap();
+        assert_eq!(inverse, vec![vec![Fr::from(1), -Fr::from(1)], vec![-Fr::from(1), Fr::from(2)]]);
+        let singular = vec![vec![Fr::from(1), Fr::from(2)], vec![Fr::from(2), Fr::from(4)]];
+        assert_eq!(invert_matrix(&singular), None);
+    }
+
+    #[test]
+    fn test_derive_optimized_constants() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        use crate::permutation::{perm, perm_optimized};
+        let constants = derive_optimized_constants::<Fr, 3, ConfigX5_254_3>().unwrap();
+        assert_eq!(constants.compressed_round_constants.len(), 3 * 8 + 57);
+        assert_eq!(constants.sparse_matrices.len(), 57);
+        for n in 0..5u64 {
+            let mut dense = [Fr::from(n), Fr::from(n + 1), Fr::from(n * n)];
+            let mut optimized = dense;
+            perm::<Fr, 3, ConfigX5_254_3>(&mut dense);
+            perm_optimized(&mut optimized, &constants);
+            assert_eq!(dense, optimized);
+        }
+    }
+}
@@ -7,10 +7,29 @@
 //! - `R_P`: The number of partial rounds.
 //! - `mds_matrix`: The MDS matrix used in the permutation.
 //! - `round_constants`: The round constants used in the permutation.
-//! - `sbox`: The S-box function used in the permutation.
+//! - `ALPHA`: The exponent of the S-box function `x^ALPHA` used in the permutation.
+//! - `sbox`: The S-box function used in the permutation (by default `x^ALPHA`).
+//! - `PARTIAL_SBOX_INDEX`: The state element to which the S-box is applied in the partial
+//!   rounds (by default the first).
+//! - `INVERSE_SBOX`: Whether the S-box is the inverse `x^-1` instead of `x^ALPHA`.
+//! - `CONSTANT_TIME_INVERSION`: Whether the inverse S-box avoids the variable-time inversion.
+//! - `HAS_INITIAL_MDS`: Whether the MDS matrix is applied once before the first round.
+//! - `ROUND_CONSTANT_WIDTH`: The number of state elements to which round constants are added
+//!   (by default all of them).
+//! - `CONSTANTS_BEFORE_SBOX`: Whether the round constants are added before the S-box layer
+//!   (as in Poseidon) or after it.
+//! - `optimized_constants`: Optionally, constants for the optimized permutation.
 
 
-use ark_ff::Field;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ark_ff::{BigInteger, Field, PrimeField};
+use once_cell::sync::Lazy;
+
+use crate::configurations::optimized_constants::{invert_matrix, OptimizedConstants};
+use crate::permutation::transpose_matrix;
 
 /// To create a specific Poseidon hash function, one needs to implement the `PoseidonConfig` trait.
 pub trait PoseidonConfig<F: Field, const T: usize> {
@@ -21,13 +40,651 @@ pub trait PoseidonConfig<F: Field, const T: usize> {
     /// The number of partial rounds in the Poseidon permutation.
     const R_P: usize;
 
+    /// The exponent of the S-box function in the Poseidon permutation. This defaults to 5,
+    /// the exponent of all BN254 and BLS12-381 configurations of this library.
+    const ALPHA: u64 = 5;
+
+    /// The index of the state element to which the S-box is applied in the partial rounds.
+    /// Standard Poseidon uses the first element, but some variants use another one.
+    const PARTIAL_SBOX_INDEX: usize = 0;
+
+    /// Whether the S-box is the inverse `x^-1` (with `0^-1 = 0`) instead of `x^ALPHA`, in
+    /// which case `ALPHA` is ignored by the default S-box. In the full rounds, the inverses
+    /// of the whole state are then computed with a single batched inversion, without calling
+    /// `sbox` (unless `CONSTANT_TIME_INVERSION` is also set).
+    const INVERSE_SBOX: bool = false;
+
+    /// Whether the inverse S-box computes every inverse with `inverse_constant_time`, instead
+    /// of the variable-time `Field::inverse` (and the batched inversion of the full rounds,
+    /// which also branches on zero elements). This is slower, and only has an effect if
+    /// `INVERSE_SBOX` is set. Since the inversion is `x^(p - 2)`, the default S-box then
+    /// panics if `F` is not a prime field.
+    const CONSTANT_TIME_INVERSION: bool = false;
+
+    /// Whether the state is multiplied with the MDS matrix once before the first round, as
+    /// in specifications with an initial linear layer. This does not affect the round
+    /// constants, of which there are still `T` per round.
+    const HAS_INITIAL_MDS: bool = false;
+
+    /// The number of leading state elements to which the round constants are added in every
+    /// round. Poseidon adds them to the whole state, but sponge variants which only add them
+    /// to the rate (the first elements of the state in this library's sponges) can set this
+    /// to the rate. The round constants still hold `T` constants per round, and those for the
+    /// remaining elements are ignored.
+    const ROUND_CONSTANT_WIDTH: usize = T;
+
+    /// Whether the round constants are added before the S-box layer in every round, as in
+    /// Poseidon, where a round is constants, S-box, MDS. Variants applying the S-box first,
+    /// i.e. S-box, constants, MDS, can set this to `false`.
+    const CONSTANTS_BEFORE_SBOX: bool = true;
+
     /// The MDS matrix used in the Poseidon permutation (the linear diffusion layer).
     fn mds_matrix() -> &'static [[F; T]; T];
 
     /// The round constants used in the Poseidon permutation.
     fn round_constants() -> &'static [F];
 
-    /// The S-box function used in the Poseidon permutation.
-    fn sbox(x: &F) -> F;
+    /// The transpose of the MDS matrix, i.e. the matrix in column-major layout, for use with
+    /// `matrix_vector_mul_transposed`. The default transposes `mds_matrix` on the first call
+    /// for each config, and returns the same matrix on all later calls.
+    fn mds_matrix_transposed() -> &'static [[F; T]; T] where Self: Sized + 'static {
+        static CACHE: TypeCache = Lazy::new(Default::default);
+        cached::<Self, _>(&CACHE, || transpose_matrix(Self::mds_matrix()))
+    }
+
+    /// The inverse of the MDS matrix, e.g. for computing the inverse permutation. The default
+    /// computes it with Gaussian elimination once per config, and panics if the MDS matrix is
+    /// singular.
+    fn mds_inverse() -> &'static [[F; T]; T] where Self: Sized + 'static {
+        static CACHE: TypeCache = Lazy::new(Default::default);
+        cached::<Self, _>(&CACHE, || {
+            let matrix: Vec<Vec<F>> = Self::mds_matrix().iter().map(|row| row.to_vec()).collect();
+            let inverse = invert_matrix(&matrix).expect("The MDS matrix should be invertible");
+            let mut result = [[F::zero(); T]; T];
+            for (row, values) in result.iter_mut().zip(inverse.iter()) {
+                row.copy_from_slice(values);
+            }
+            result
+        })
+    }
+
+    /// The round constants, shaped as one row of `T` constants per round, i.e. `R_F + R_P`
+    /// rows. Panics if the number of round constants is not `T * (R_F + R_P)`.
+    fn round_constants_matrix() -> Vec<[F; T]> {
+        let constants = Self::round_constants();
+        assert_eq!(constants.len(), num_round_constants(T, Self::R_F, Self::R_P), "The number of round constants should be T * (R_F + R_P)");
+        constants
+            .chunks_exact(T)
+            .map(|row| {
+                let mut shaped = [F::zero(); T];
+                shaped.copy_from_slice(row);
+                shaped
+            })
+            .collect()
+    }
+
+    /// The optimized constants used by `perm_auto` to compute the permutation with sparse
+    /// matrices in the partial rounds (see `perm_optimized`). By default, a config does not
+    /// provide them, and `perm_auto` falls back to the dense permutation `perm`. They can be
+    /// derived from the config with `derive_optimized_constants`.
+    fn optimized_constants() -> Option<&'static OptimizedConstants<F, T>> {
+        None
+    }
+
+    /// The S-box function used in the Poseidon permutation. By default, this computes
+    /// `x^ALPHA`, using a fixed addition chain when `ALPHA == 5`, or `x^-1` if
+    /// `INVERSE_SBOX` is set.
+    /// 
+    /// Overriding this is not supported for configs which set `INVERSE_SBOX` but not
+    /// `CONSTANT_TIME_INVERSION`, since their full rounds use a batched inversion instead, so
+    /// the override would only be applied in the partial rounds.
+    fn sbox(x: &F) -> F {
+        if Self::INVERSE_SBOX && Self::CONSTANT_TIME_INVERSION {
+            inverse_constant_time_in_base_field(x)
+        } else if Self::INVERSE_SBOX {
+            x.inverse().unwrap_or(F::zero())
+        } else if Self::ALPHA == 5 {
+            sbox_pow5(x)
+        } else {
+            x.pow([Self::ALPHA])
+        }
+    }
+
+    /// An estimate of the number of field multiplications (including squarings) performed by
+    /// the permutation `perm`: `T^2` for the MDS multiplication of every round, plus the cost
+    /// of `T` S-boxes per full round and one S-box per partial round (see `sbox_mults`).
+    /// 
+    /// Additions are not counted, and neither are the savings of `perm_optimized`. If `sbox`
+    /// is overridden, the estimate still assumes the default S-box.
+    fn estimated_mults() -> usize {
+        let sbox = sbox_mults(Self::ALPHA);
+        let full_round = T * sbox + T * T;
+        let partial_round = sbox + T * T;
+        Self::R_F * full_round + Self::R_P * partial_round
+    }
+
+    /// An estimate of the number of R1CS constraints of a circuit computing the permutation:
+    /// one constraint per multiplication of an S-box (see `sbox_mults`), for `T` S-boxes per
+    /// full round and one per partial round. The round constants and the linear layers are
+    /// free in R1CS, since they only form linear combinations.
+    /// 
+    /// With `INVERSE_SBOX`, every S-box instead costs two constraints: the multiplication of
+    /// the input with its claimed inverse, and the check which allows the inverse of zero to
+    /// be zero.
+    /// 
+    /// Circuits can save constraints on S-boxes of inputs known at compile time. E.g.
+    /// circomlib's Poseidon with two inputs reports 240 constraints instead of the 243 given
+    /// here for `ConfigX5_254_3`, since the first element of its initial state is the constant
+    /// zero. As for `estimated_mults`, an overridden `sbox` is not taken into account.
+    fn r1cs_constraint_estimate() -> usize {
+        let sbox = if Self::INVERSE_SBOX { 2 } else { sbox_mults(Self::ALPHA) };
+        sbox * (T * Self::R_F + Self::R_P)
+    }
+
+    /// The degree of the S-box, i.e. `ALPHA`, for budgeting the constraints of the permutation
+    /// in circuit backends (see also `estimated_mults`). Since `ALPHA` is ignored by the
+    /// inverse S-box, configs setting `INVERSE_SBOX` should override this.
+    fn sbox_degree() -> u64 {
+        Self::ALPHA
+    }
+
+    /// Checks that the round numbers meet the security margin of the Poseidon paper for the
+    /// given security level: after removing the margin of two full rounds and 7.5% of the
+    /// partial rounds, the remaining rounds must still satisfy the bounds of
+    /// `satisfies_round_bounds`.
+    /// 
+    /// The bounds only cover the `x^ALPHA` S-box over prime fields, so this returns `false`
+    /// for configs with the inverse S-box.
+    fn meets_security_margin(security_bits: usize) -> bool {
+        if Self::INVERSE_SBOX || Self::R_F < 2 {
+            return false;
+        }
+        // The largest number of partial rounds `r` with `ceil(1.075 * r) <= R_P`.
+        let r_p = Self::R_P * 1000 / 1075;
+        satisfies_round_bounds(F::BasePrimeField::MODULUS_BIT_SIZE, T, Self::R_F - 2, r_p, Self::ALPHA, security_bits)
+    }
+
+}
+
+/// Values computed once per type, which the default methods of `PoseidonConfig` use to return
+/// `&'static` values, since statics inside a generic function are shared by all of its
+/// instantiations.
+type TypeCache = Lazy<Mutex<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>>;
+
+/// Returns the value of type `V` cached in `cache` for the type `K`, computing and leaking it
+/// on the first call. The value is computed without holding the lock, so `init` may use other
+/// caches, and if two threads race, the value of the first one is kept.
+fn cached<K: 'static, V: Send + Sync + 'static>(cache: &'static TypeCache, init: impl FnOnce() -> V) -> &'static V {
+    let key = TypeId::of::<(K, V)>();
+    let value = cache.lock().expect("The cache should not be poisoned").get(&key).copied();
+    let value = value.unwrap_or_else(|| {
+        let value = init();
+        *cache.lock().expect("The cache should not be poisoned").entry(key).or_insert_with(|| Box::leak(Box::new(value)))
+    });
+    value.downcast_ref().expect("The cached value should have the type of its key")
+}
+
+/// The number of multiplications (including squarings) used by the default S-box `x^alpha`:
+/// three for the addition chain of `x^5`, and otherwise the squarings and multiplications of
+/// square-and-multiply.
+pub const fn sbox_mults(alpha: u64) -> usize {
+    if alpha == 5 {
+        3
+    } else if alpha == 0 {
+        0
+    } else {
+        (63 - alpha.leading_zeros() + alpha.count_ones() - 1) as usize
+    }
+}
+
+/// The number of round constants used by a Poseidon permutation with state size `t`, `r_f`
+/// full rounds and `r_p` partial rounds.
+/// 
+/// Since this is a `const fn`, it can be used to check a configuration at compile time, e.g.
+/// `const _: () = assert!(num_round_constants(3, 8, 57) == 195);`.
+pub const fn num_round_constants(t: usize, r_f: usize, r_p: usize) -> usize {
+    t * (r_f + r_p)
+}
+
+/// Checks that the round numbers are valid for a Poseidon permutation with state size `t`.
+/// Since half of the full rounds are performed before the partial rounds and half of them
+/// after, the number of full rounds must be even.
+pub const fn is_valid_round_configuration(t: usize, r_f: usize) -> bool {
+    t > 0 && r_f.is_multiple_of(2)
+}
+
+/// Checks that `x^alpha` is a non-linear permutation of the prime field `F`, i.e. that
+/// `alpha > 1` and `gcd(alpha, p - 1) = 1`. For example, this holds for `alpha = 5` over
+/// BN254, but not for `alpha = 3`, since 3 divides `p - 1`.
+pub fn is_valid_sbox_exponent<F: PrimeField>(alpha: u64) -> bool {
+    if alpha < 2 {
+        return false;
+    }
+    // Reduce the modulus, from its most significant limb down, to get `(p - 1) mod alpha`.
+    let modulus = F::MODULUS;
+    let remainder = modulus.as_ref().iter().rev()
+        .fold(0u128, |remainder, limb| ((remainder << 64) + *limb as u128) % alpha as u128) as u64;
+    let (mut a, mut b) = (alpha, (remainder + alpha - 1) % alpha);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a == 1
+}
+
+/// Checks the bounds on the round numbers of the Poseidon paper (including the later attack of
+/// https://eprint.iacr.org/2023/537), for the S-box `x^alpha` with `alpha >= 3` over a prime
+/// field of `field_bits` bits, following `calc_round_numbers.py` of the reference
+/// implementation. The bounds are, in order: the statistical attacks, interpolation, three
+/// Groebner basis attacks, and the improved Groebner basis attack. The logarithm of the
+/// modulus is approximated by its bit size.
+/// 
+/// These bounds do not include the security margin, see `meets_security_margin`.
+pub fn satisfies_round_bounds(field_bits: u32, t: usize, r_f: usize, r_p: usize, alpha: u64, security_bits: usize) -> bool {
+    // The statistical bound alone requires at least six full rounds.
+    if alpha < 3 || t < 2 || r_f < 6 {
+        return false;
+    }
+    let (n, t_f, r_f_f, r_p_f, m) = (field_bits as f64, t as f64, r_f as f64, r_p as f64, security_bits as f64);
+    let log2_alpha = (alpha as f64).log2();
+    let statistical = if m <= (n - (alpha as f64 - 1.0) / 2.0).floor() * (t_f + 1.0) { 6.0 } else { 10.0 };
+    let interpolation = 1.0 + (m.min(n) / log2_alpha).ceil() + (t_f.ln() / (alpha as f64).ln()).ceil() - r_p_f;
+    let groebner_1 = m.min(n) / log2_alpha - r_p_f;
+    let groebner_2 = t_f - 1.0 + (m / (t_f + 1.0)).min(n / 2.0) / log2_alpha - r_p_f;
+    let groebner_3 = (t_f - 2.0 + m / (2.0 * log2_alpha) - r_p_f) / (t_f - 1.0);
+    let min_r_f = [statistical, interpolation, groebner_1, groebner_2, groebner_3]
+        .into_iter()
+        .map(f64::ceil)
+        .fold(f64::MIN, f64::max);
+
+    let r_temp = (t / 3) as u64;
+    let over = (r_f as u64 - 1) * t as u64 + 2 * r_p as u64 + r_temp * (1 + r_f as u64 / 2) + alpha;
+    let under = r_temp * (r_f as u64 / 2) + r_p as u64 + alpha;
+    // log2 of the binomial coefficient `over` choose `under`.
+    let binomial_log2: f64 = (1..=under).map(|i| ((over - under + i) as f64 / i as f64).log2()).sum();
+    r_f_f >= min_r_f && (2.0 * binomial_log2).ceil() >= m
+}
+
+/// Computes `x^5` using the addition chain `x^2`, `x^4`, `x^5`, which is faster than the
+/// generic square-and-multiply of `pow`, and avoids branching on the exponent.
+pub fn sbox_pow5<F: Field>(x: &F) -> F {
+    let x2 = x.square();
+    let x4 = x2.square();
+    x4 * x
+}
+
+/// Inverts all elements in place, mapping zero to zero, using Montgomery's trick: a single
+/// field inversion and `3(T - 1)` multiplications, instead of `T` inversions.
+pub fn batch_inverse<F: Field, const T: usize>(values: &mut [F; T]) {
+    // prefix[i] is the product of the non-zero elements among values[..i].
+    let mut prefix = [F::one(); T];
+    let mut product = F::one();
+    for (partial, value) in prefix.iter_mut().zip(values.iter()) {
+        *partial = product;
+        if !value.is_zero() {
+            product *= value;
+        }
+    }
+    // The product of non-zero elements is non-zero, so the inverse exists.
+    let mut inverse = product.inverse().expect("product of non-zero elements is non-zero");
+    for (value, partial) in values.iter_mut().zip(prefix.iter()).rev() {
+        if !value.is_zero() {
+            let value_inverse = inverse * partial;
+            inverse *= *value;
+            *value = value_inverse;
+        }
+    }
+}
+
+/// Inverts an element of a prime field as `x^(p - 2)`, mapping zero to zero, with a sequence
+/// of field operations which does not depend on `x`: the exponent is public, so the
+/// square-and-multiply of `Field::pow` only branches on its bits. In contrast,
+/// `Field::inverse` uses a binary extended Euclidean algorithm, whose running time depends
+/// on the input.
+/// 
+/// Note that this only removes the data-dependent control flow of the inversion, while the
+/// field arithmetic of arkworks itself makes no constant-time guarantees.
+pub fn inverse_constant_time<F: PrimeField>(x: &F) -> F {
+    x.pow(inverse_exponent::<F>())
+}
+
+/// The exponent `p - 2` of `inverse_constant_time`. It is derived from the constant
+/// `F::MODULUS` on the stack, so it does not allocate, and is folded to a constant for a
+/// concrete field.
+#[inline(always)]
+fn inverse_exponent<F: PrimeField>() -> F::BigInt {
+    let mut exponent = F::MODULUS;
+    exponent.sub_with_borrow(&F::BigInt::from(2u64));
+    exponent
+}
+
+/// Inverts `x` with `inverse_constant_time` in the prime field underlying `F`, for the
+/// default S-box, which is only bounded by `Field`. Panics if `F` is an extension field.
+fn inverse_constant_time_in_base_field<F: Field>(x: &F) -> F {
+    assert_eq!(F::extension_degree(), 1, "Constant-time inversion requires a prime field");
+    let base = x.to_base_prime_field_elements().next().expect("A prime field element has one base field element");
+    F::from_base_prime_field(inverse_constant_time(&base))
+}
+
+/// Builds the little-endian `u64` limbs of an exponent given as a `u128`, in the form expected
+/// by `Field::pow`.
+/// 
+/// Since `ALPHA` is a `u64`, a config with an S-box exponent of `2^64` or more must override
+/// `sbox`, e.g. with `x.pow(exponent_limbs_u128(EXPONENT))`.
+pub const fn exponent_limbs_u128(alpha: u128) -> [u64; 2] {
+    [alpha as u64, (alpha >> 64) as u64]
+}
+
+/// Computes a short fingerprint of the parameters of a config, for checking that two
+/// implementations use the same constants, e.g. when debugging interoperability.
+/// 
+/// The fingerprint is the hex encoding of the first 8 bytes of the SHA3-256 hash of `T`,
+/// `R_F`, `R_P`, `ALPHA`, `INVERSE_SBOX`, `CONSTANT_TIME_INVERSION`, `PARTIAL_SBOX_INDEX`,
+/// `HAS_INITIAL_MDS`, `ROUND_CONSTANT_WIDTH` and `CONSTANTS_BEFORE_SBOX` (as little-endian
+/// `u64`s, with `0` and `1` for the flags), followed by the round constants and the MDS
+/// matrix (row by row) in their compressed arkworks serialization. A hash other than
+/// Poseidon is used, so that the fingerprint does not depend on the parameters it describes.
+pub fn config_fingerprint<F: Field, C: PoseidonConfig<F, T>, const T: usize>() -> String {
+    use sha3::{Digest, Sha3_256};
+    let mut bytes = Vec::new();
+    let parameters = [
+        T as u64,
+        C::R_F as u64,
+        C::R_P as u64,
+        C::ALPHA,
+        C::INVERSE_SBOX as u64,
+        C::CONSTANT_TIME_INVERSION as u64,
+        C::PARTIAL_SBOX_INDEX as u64,
+        C::HAS_INITIAL_MDS as u64,
+        C::ROUND_CONSTANT_WIDTH as u64,
+        C::CONSTANTS_BEFORE_SBOX as u64,
+    ];
+    for parameter in parameters {
+        bytes.extend_from_slice(&parameter.to_le_bytes());
+    }
+    for element in C::round_constants().iter().chain(C::mds_matrix().iter().flatten()) {
+        element.serialize_compressed(&mut bytes).expect("Serializing into a Vec should not fail");
+    }
+    hex::encode(&Sha3_256::digest(&bytes)[..8])
+}
+
+/// Builds the little-endian `u64` limbs of an exponent given as a `BigInteger`, in the form
+/// expected by `Field::pow`. Leading zero limbs are removed.
+pub fn exponent_limbs<B: BigInteger>(alpha: &B) -> Vec<u64> {
+    let limbs = alpha.as_ref();
+    let len = limbs.iter().rposition(|limb| *limb != 0).map_or(0, |index| index + 1);
+    limbs[..len].to_vec()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::{PrimeField, Zero};
+    use ark_std::{test_rng, UniformRand};
+    use once_cell::sync::Lazy;
+
+    const TEST_T: usize = 2;
+    const TEST_R_F: usize = 2;
+    const TEST_R_P: usize = 1;
+    const TEST_CONSTANTS: [u64; 6] = [1, 2, 3, 4, 5, 6];
+
+    /// A config whose parameters are validated at compile time.
+    struct CompileTimeCheckedConfig;
+    impl PoseidonConfig<Fr, TEST_T> for CompileTimeCheckedConfig {
+        const R_F: usize = TEST_R_F;
+        const R_P: usize = TEST_R_P;
+        const ALPHA: u64 = 5;
+
+        fn mds_matrix() -> &'static [[Fr; TEST_T]; TEST_T] {
+            static MDS_MATRIX: Lazy<[[Fr; 2]; 2]> = Lazy::new(|| {
+                [[Fr::from(2), Fr::from(1)],
+                 [Fr::from(1), Fr::from(2)]]
+            });
+            &MDS_MATRIX
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            static ROUND_CONSTANTS: Lazy<Vec<Fr>> = Lazy::new(|| {
+                TEST_CONSTANTS.iter().map(|c| Fr::from(*c)).collect()
+            });
+            &ROUND_CONSTANTS
+        }
+    }
+
+    const _: () = assert!(TEST_CONSTANTS.len() == num_round_constants(TEST_T, TEST_R_F, TEST_R_P));
+    const _: () = assert!(is_valid_round_configuration(TEST_T, <CompileTimeCheckedConfig as PoseidonConfig<Fr, TEST_T>>::R_F));
+
+    #[test]
+    fn test_compile_time_checked_config() {
+        assert_eq!(num_round_constants(3, 8, 57), 195);
+        assert!(!is_valid_round_configuration(3, 7));
+    }
+
+    #[test]
+    fn test_is_valid_sbox_exponent() {
+        assert!(is_valid_sbox_exponent::<Fr>(5));
+        assert!(is_valid_sbox_exponent::<Fr>(7));
+        for alpha in [0, 1, 2, 3, 13] {
+            assert!(!is_valid_sbox_exponent::<Fr>(alpha));
+        }
+        // 100 = 2^2 * 5^2, so `x^3` permutes F101 while `x^5` does not.
+        assert!(is_valid_sbox_exponent::<crate::test_fields::F101>(3));
+        assert!(!is_valid_sbox_exponent::<crate::test_fields::F101>(5));
+        let round_constants = CompileTimeCheckedConfig::round_constants();
+        assert_eq!(round_constants.len(), num_round_constants(TEST_T, CompileTimeCheckedConfig::R_F, CompileTimeCheckedConfig::R_P));
+    }
+
+    #[test]
+    fn test_round_constants_matrix() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        let rows = ConfigX5_254_3::round_constants_matrix();
+        assert_eq!(rows.len(), ConfigX5_254_3::R_F + ConfigX5_254_3::R_P);
+        let flat = ConfigX5_254_3::round_constants();
+        assert_eq!(rows[0], [flat[0], flat[1], flat[2]]);
+        assert_eq!(rows[64], [flat[192], flat[193], flat[194]]);
+        assert_eq!(rows.concat(), flat);
+
+        let rows = CompileTimeCheckedConfig::round_constants_matrix();
+        assert_eq!(rows, vec![[Fr::from(1), Fr::from(2)], [Fr::from(3), Fr::from(4)], [Fr::from(5), Fr::from(6)]]);
+    }
+
+    #[test]
+    fn test_mds_inverse() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        fn assert_inverse<P: PoseidonConfig<Fr, 3> + 'static>() {
+            let matrix = P::mds_matrix();
+            let inverse = P::mds_inverse();
+            for (i, row) in matrix.iter().enumerate() {
+                for j in 0..3 {
+                    let product: Fr = row.iter().zip(inverse.iter()).map(|(m, inv)| *m * inv[j]).sum();
+                    assert_eq!(product, if i == j { Fr::from(1) } else { Fr::zero() });
+                }
+            }
+        }
+        assert_inverse::<ConfigX5_254_3>();
+        assert_eq!(ConfigX5_254_3::mds_inverse(), ConfigX5_254_3::mds_inverse());
+        assert_eq!(CompileTimeCheckedConfig::mds_inverse()[0][0] * Fr::from(3), Fr::from(2));
+        // The default computes the inverse only once.
+        assert!(std::ptr::eq(CompileTimeCheckedConfig::mds_inverse(), CompileTimeCheckedConfig::mds_inverse()));
+    }
+
+    #[test]
+    fn test_mds_matrix_transposed() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        assert_eq!(ConfigX5_254_3::mds_matrix_transposed(), &transpose_matrix(ConfigX5_254_3::mds_matrix()));
+        let transposed = CompileTimeCheckedConfig::mds_matrix_transposed();
+        assert_eq!(transposed, &transpose_matrix(CompileTimeCheckedConfig::mds_matrix()));
+        assert!(std::ptr::eq(transposed, CompileTimeCheckedConfig::mds_matrix_transposed()));
+        // The cache is keyed by the config, so the default of another config is not affected.
+        assert_ne!(transposed.as_ptr(), ConfigX5_254_3::mds_matrix_transposed().as_ptr().cast());
+    }
+
+    #[test]
+    fn test_batch_inverse() {
+        let mut rng = test_rng();
+        let mut values = [Fr::rand(&mut rng), Fr::zero(), Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::zero()];
+        let expected = values.map(|x| x.inverse().unwrap_or(Fr::zero()));
+        batch_inverse(&mut values);
+        assert_eq!(values, expected);
+
+        let mut zeroes = [Fr::zero(); 3];
+        batch_inverse(&mut zeroes);
+        assert_eq!(zeroes, [Fr::zero(); 3]);
+    }
+
+    #[test]
+    fn test_estimated_mults() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        assert_eq!(sbox_mults(5), 3);
+        assert_eq!(sbox_mults(3), 2);
+        assert_eq!(sbox_mults(17), 5);
+        assert_eq!(sbox_mults(1), 0);
+        // 8 full rounds with 3 S-boxes and 9 MDS multiplications, and 57 partial rounds with
+        // one S-box and 9 MDS multiplications.
+        assert_eq!(ConfigX5_254_3::estimated_mults(), 8 * (3 * 3 + 9) + 57 * (3 + 9));
+        assert_eq!(ConfigX5_254_3::estimated_mults(), 828);
+    }
+
+    #[test]
+    fn test_meets_security_margin() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        use crate::configurations::config_x5_254_4::ConfigX5_254_4;
+        assert!(ConfigX5_254_3::meets_security_margin(128));
+        assert!(ConfigX5_254_4::meets_security_margin(128));
+        // With R_F = 6, the interpolation bound requires at least 52 partial rounds for t = 3.
+        assert!(satisfies_round_bounds(254, 3, 6, 52, 5, 128));
+        assert!(!satisfies_round_bounds(254, 3, 6, 51, 5, 128));
+        assert!(!satisfies_round_bounds(254, 3, 4, 57, 5, 128));
+        assert!(!CompileTimeCheckedConfig::meets_security_margin(128));
+    }
+
+    #[test]
+    fn test_config_fingerprint() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        let fingerprint = config_fingerprint::<Fr, ConfigX5_254_3, 3>();
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(fingerprint, config_fingerprint::<Fr, ConfigX5_254_3, 3>());
+        assert_eq!(fingerprint, "b57edd046bc8c667");
+        assert_ne!(config_fingerprint::<Fr, CompileTimeCheckedConfig, TEST_T>(), fingerprint);
+
+        /// The x5_254_3 config with the flag of the given index changed from its default.
+        struct FlaggedConfig<const FLAG: usize>;
+        impl<const FLAG: usize> PoseidonConfig<Fr, 3> for FlaggedConfig<FLAG> {
+            const R_F: usize = 8;
+            const R_P: usize = 57;
+            const INVERSE_SBOX: bool = FLAG == 0 || FLAG == 1;
+            const CONSTANT_TIME_INVERSION: bool = FLAG == 1;
+            const PARTIAL_SBOX_INDEX: usize = if FLAG == 2 { 1 } else { 0 };
+            const HAS_INITIAL_MDS: bool = FLAG == 3;
+            const ROUND_CONSTANT_WIDTH: usize = if FLAG == 4 { 1 } else { 3 };
+            const CONSTANTS_BEFORE_SBOX: bool = FLAG != 5;
+
+            fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+                ConfigX5_254_3::mds_matrix()
+            }
+
+            fn round_constants() -> &'static [Fr] {
+                ConfigX5_254_3::round_constants()
+            }
+        }
+        let flagged = [
+            config_fingerprint::<Fr, FlaggedConfig<0>, 3>(),
+            config_fingerprint::<Fr, FlaggedConfig<1>, 3>(),
+            config_fingerprint::<Fr, FlaggedConfig<2>, 3>(),
+            config_fingerprint::<Fr, FlaggedConfig<3>, 3>(),
+            config_fingerprint::<Fr, FlaggedConfig<4>, 3>(),
+            config_fingerprint::<Fr, FlaggedConfig<5>, 3>(),
+        ];
+        assert_eq!(config_fingerprint::<Fr, FlaggedConfig<6>, 3>(), fingerprint);
+        for (i, a) in flagged.iter().enumerate() {
+            assert_ne!(a, &fingerprint);
+            for b in &flagged[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_r1cs_constraint_estimate() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        // circomlib's Poseidon(2) has 240 constraints, plus the three for the S-box of the
+        // constant first element in the first round.
+        assert_eq!(ConfigX5_254_3::r1cs_constraint_estimate(), 240 + 3);
+        assert_eq!(CompileTimeCheckedConfig::r1cs_constraint_estimate(), 3 * (2 * 2 + 1));
+
+        struct InverseSboxConfig;
+        impl PoseidonConfig<Fr, 3> for InverseSboxConfig {
+            const R_F: usize = 8;
+            const R_P: usize = 57;
+            const INVERSE_SBOX: bool = true;
+
+            fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+                ConfigX5_254_3::mds_matrix()
+            }
+
+            fn round_constants() -> &'static [Fr] {
+                ConfigX5_254_3::round_constants()
+            }
+        }
+        assert_eq!(InverseSboxConfig::r1cs_constraint_estimate(), 2 * (3 * 8 + 57));
+    }
+
+    #[test]
+    fn test_sbox_degree() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        assert_eq!(ConfigX5_254_3::sbox_degree(), 5);
+    }
+
+    #[test]
+    fn test_sbox_pow5() {
+        let mut rng = test_rng();
+        for _ in 0..100 {
+            let x = Fr::rand(&mut rng);
+            assert_eq!(sbox_pow5(&x), x.pow([5]));
+        }
+    }
+
+    #[test]
+    fn test_inverse_constant_time() {
+        let mut rng = test_rng();
+        for _ in 0..20 {
+            let x = Fr::rand(&mut rng);
+            assert_eq!(inverse_constant_time(&x), x.inverse().unwrap());
+        }
+        assert_eq!(inverse_constant_time(&Fr::zero()), Fr::zero());
+        assert_eq!(inverse_constant_time(&Fr::from(1)), Fr::from(1));
+        // Another prime field, the BN254 base field.
+        let x = ark_bn254::Fq::rand(&mut rng);
+        assert_eq!(inverse_constant_time(&x), x.inverse().unwrap());
+        assert_eq!(inverse_exponent::<Fr>(), (-Fr::from(2)).into_bigint());
+        assert_eq!(inverse_constant_time_in_base_field(&x), x.inverse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Constant-time inversion requires a prime field")]
+    fn test_inverse_constant_time_in_extension_field() {
+        inverse_constant_time_in_base_field(&ark_bn254::Fq2::from(2u64));
+    }
+
+    #[test]
+    fn test_exponent_limbs() {
+        assert_eq!(exponent_limbs_u128(5), [5, 0]);
+        assert_eq!(exponent_limbs_u128((1 << 64) + 1), [1, 1]);
+
+        // x^(2^64 + 1) is x squared 64 times, times x.
+        let mut rng = test_rng();
+        let x = Fr::rand(&mut rng);
+        let mut expected = x;
+        for _ in 0..64 {
+            expected.square_in_place();
+        }
+        expected *= x;
+        assert_eq!(x.pow(exponent_limbs_u128((1 << 64) + 1)), expected);
 
+        // By Fermat's little theorem, x^p = x, where the exponent p has four limbs.
+        assert_eq!(exponent_limbs(&Fr::MODULUS).len(), 4);
+        assert_eq!(x.pow(exponent_limbs(&Fr::MODULUS)), x);
+        assert_eq!(exponent_limbs(&<Fr as PrimeField>::BigInt::from(5u64)), vec![5]);
+    }
 }
@@ -0,0 +1,210 @@
+//! This module provides a registry of the BN254 configurations of this library, which can be
+//! looked up by name at runtime, e.g. from a serialized config identifier.
+//!
+//! A config looked up with `get_config` is returned as a `RuntimeConfig` (see the
+//! `runtime_config` module), which holds the parameters of the config as plain data,
+//! together with its permutation. Since the field is
+//! part of the type, only the configurations over the BN254 scalar field are registered (in
+//! particular, `x5_255_3` is not).
+//!
+//! A `RuntimeConfig` can also be parsed from a single combined parameter file with
+//! `parse_combined_params`, e.g. for distributing a new parameter set as one file.
+
+use std::collections::HashMap;
+
+use ark_bn254::Fr;
+
+use crate::configurations::config_x5_254_2::ConfigX5_254_2;
+use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+use crate::configurations::config_x5_254_4::ConfigX5_254_4;
+use crate::configurations::config_x5_254_5::ConfigX5_254_5;
+use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+use crate::configurations::config_creation_helper_functions::{parse_sections, try_hex_to_field};
+use crate::configurations::optimized_constants::{ImportError, section_lines, section_tokens};
+use crate::configurations::poseidon_config::is_valid_sbox_exponent;
+use crate::configurations::runtime_config::RuntimeConfig;
+use crate::field_bytes::FieldBytes;
+
+/// The names of the registered configurations.
+pub const CONFIG_NAMES: [&str; 5] = ["x5_254_2", "x5_254_3", "x5_254_4", "x5_254_5", "x5_254_6"];
+
+/// Looks up a configuration by name, or returns `None` if there is no such configuration.
+pub fn get_config(name: &str) -> Option<RuntimeConfig<Fr>> {
+    match name {
+        "x5_254_2" => Some(RuntimeConfig::from_config::<2, ConfigX5_254_2>("x5_254_2")),
+        "x5_254_3" => Some(RuntimeConfig::from_config::<3, ConfigX5_254_3>("x5_254_3")),
+        "x5_254_4" => Some(RuntimeConfig::from_config::<4, ConfigX5_254_4>("x5_254_4")),
+        "x5_254_5" => Some(RuntimeConfig::from_config::<5, ConfigX5_254_5>("x5_254_5")),
+        "x5_254_6" => Some(RuntimeConfig::from_config::<6, ConfigX5_254_6>("x5_254_6")),
+        _ => None,
+    }
+}
+
+/// The name of the configs parsed with `parse_combined_params`.
+pub const PARSED_CONFIG_NAME: &str = "parsed";
+
+/// The keys of the `[params]` section of a combined parameter file.
+const PARAMS: [&str; 3] = ["r_f", "r_p", "alpha"];
+
+/// Parses whitespace-separated hex-encoded field elements.
+fn parse_hex_elements<F: FieldBytes>(line: &str) -> Result<Vec<F>, ImportError> {
+    line.split_whitespace().map(|token| try_hex_to_field(token).map_err(ImportError::InvalidElement)).collect()
+}
+
+/// Parses a config from a combined parameter file, with the sections:
+///
+/// - `[params]`: lines of the form `key = value` for the keys `r_f` and `r_p`, and optionally
+///   `alpha` (by default 5).
+/// - `[matrix]`: the MDS matrix, one row of space-separated elements per line. The state
+///   width `t` is the number of rows.
+/// - `[constants]`: the `t * (r_f + r_p)` round constants, separated by whitespace (e.g.
+///   one per line, as in the `constants.txt` files).
+///
+/// The field elements are big-endian hex strings, as in the other parameter files, and lines
+/// starting with `#` are comments. Unknown or repeated keys in `[params]` are rejected. The
+/// state width must be at least 2, `r_f` must be even, since the full rounds are split
+/// evenly around the partial rounds, and `x^alpha` must be a permutation of the field (see
+/// `is_valid_sbox_exponent`). The config is
+/// named `PARSED_CONFIG_NAME`, and has no specialized permutation, so it is permuted with
+/// `perm_dyn`.
+pub fn parse_combined_params(raw: &str) -> Result<RuntimeConfig<Fr>, ImportError> {
+    let sections = parse_sections(raw);
+
+    let mut params = HashMap::new();
+    for line in section_lines(&sections, "params")? {
+        let (key, value) = line.split_once('=').ok_or_else(|| ImportError::InvalidElement(line.to_string()))?;
+        let value: u64 = value.trim().parse().map_err(|_| ImportError::InvalidElement(line.to_string()))?;
+        let key = key.trim();
+        if !PARAMS.contains(&key) {
+            return Err(ImportError::InvalidParameter(format!("unknown parameter {}", key)));
+        }
+        if params.insert(key, value).is_some() {
+            return Err(ImportError::InvalidParameter(format!("duplicate parameter {}", key)));
+        }
+    }
+    let param = |key: &str| params.get(key).copied().ok_or_else(|| ImportError::InvalidParameter(format!("missing parameter {}", key)));
+    let r_f = param("r_f")? as usize;
+    let r_p = param("r_p")? as usize;
+    let alpha = param("alpha").unwrap_or(5);
+    if !r_f.is_multiple_of(2) {
+        return Err(ImportError::InvalidParameter(format!("r_f = {}", r_f)));
+    }
+    if !is_valid_sbox_exponent::<Fr>(alpha) {
+        return Err(ImportError::InvalidParameter(format!("alpha = {}", alpha)));
+    }
+
+    let mds_matrix = section_lines(&sections, "matrix")?
+        .into_iter()
+        .map(parse_hex_elements)
+        .collect::<Result<Vec<Vec<Fr>>, ImportError>>()?;
+    let t = mds_matrix.len();
+    if t < 2 {
+        return Err(ImportError::InvalidParameter(format!("t = {}", t)));
+    }
+    if let Some(row) = mds_matrix.iter().find(|row| row.len() != t) {
+        return Err(ImportError::WrongNumberOfElements { section: "matrix".to_string(), expected: t, found: row.len() });
+    }
+
+    let round_constants = section_tokens(&sections, "constants")?
+        .into_iter()
+        .map(|token| try_hex_to_field(token).map_err(ImportError::InvalidElement))
+        .collect::<Result<Vec<Fr>, ImportError>>()?;
+    if round_constants.len() != t * (r_f + r_p) {
+        return Err(ImportError::WrongNumberOfElements { section: "constants".to_string(), expected: t * (r_f + r_p), found: round_constants.len() });
+    }
+
+    Ok(RuntimeConfig {
+        name: PARSED_CONFIG_NAME,
+        t,
+        r_f,
+        r_p,
+        alpha,
+        partial_sbox_index: 0,
+        inverse_sbox: false,
+        has_initial_mds: false,
+        round_constant_width: t,
+        constants_before_sbox: true,
+        mds_matrix,
+        round_constants,
+        permutation: None,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permutation::perm;
+    use crate::x5_254_3;
+
+    #[test]
+    fn test_get_config() {
+        let config = get_config("x5_254_3").unwrap();
+        assert_eq!((config.t, config.r_f, config.r_p, config.alpha), (3, 8, 57, 5));
+        assert_eq!(config.round_constants.len(), 195);
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        assert_eq!(config.hash(&input), x5_254_3::hash(&input)[0]);
+
+        let mut state = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        config.permute(&mut state);
+        let mut expected = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        assert_eq!(state, expected);
+
+        for name in CONFIG_NAMES {
+            assert_eq!(get_config(name).unwrap().name, name);
+        }
+        assert_eq!(get_config("x5_254_4").unwrap().t, 4);
+        assert!(get_config("x5_254_1").is_none());
+    }
+    /// The x5_254_3 parameters as a combined parameter file.
+    fn combined_x5_254_3() -> String {
+        format!(
+            "# x5_254_3\n[params]\nr_f = 8\nr_p = 57\nalpha = 5\n[matrix]\n{}\n{}\n{}\n[constants]\n{}",
+            "109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b 16ed41e13bb9c0c66ae119424fddbcbc9314dc9fdbdeea55d6c64543dc4903e0 2b90bba00fca0589f617e7dcbfe82e0df706ab640ceb247b791a93b74e36736d",
+            "2969f27eed31a480b9c36c764379dbca2cc8fdd1415c3dded62940bcde0bd771 2e2419f9ec02ec394c9871c832963dc1b89d743c8c7b964029b2311687b1fe23 101071f0032379b697315876690f053d148d4e109f5fb065c8aacc55a0f89bfa",
+            "143021ec686a3f330d5f9e654638065ce6cd79e28c5b3753326244ee65a1b1a7 176cc029695ad02582a70eff08a6fd99d057e12e58e7d7b6b16cdfabc8ee2911 19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0",
+            include_str!("config_x5_254_3/constants.txt"),
+        )
+    }
+
+    #[test]
+    fn test_parse_combined_params() {
+        let config = parse_combined_params(&combined_x5_254_3()).unwrap();
+        let expected = get_config("x5_254_3").unwrap();
+        assert_eq!((config.name, config.t, config.r_f, config.r_p, config.alpha), (PARSED_CONFIG_NAME, 3, 8, 57, 5));
+        assert_eq!(config.mds_matrix, expected.mds_matrix);
+        assert_eq!(config.round_constants, expected.round_constants);
+        assert!(config.permutation.is_none());
+
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        assert_eq!(config.hash(&input), x5_254_3::hash(&input)[0]);
+        let mut state = input;
+        config.permute(&mut state);
+        let mut expected_state = input;
+        expected.permute(&mut expected_state);
+        assert_eq!(state, expected_state);
+    }
+
+    #[test]
+    fn test_parse_combined_params_errors() {
+        let raw = combined_x5_254_3();
+        assert_eq!(parse_combined_params(&raw.replace("r_p = 57", "r_p = 56")).unwrap_err(),
+            ImportError::WrongNumberOfElements { section: "constants".to_string(), expected: 192, found: 195 });
+        assert_eq!(parse_combined_params(&raw.replace("r_f = 8\n", "")).unwrap_err(), ImportError::InvalidParameter("missing parameter r_f".to_string()));
+        assert_eq!(parse_combined_params(&raw.replace("[matrix]", "[mds]")).unwrap_err(), ImportError::MissingSection("matrix".to_string()));
+        assert_eq!(parse_combined_params(&raw.replace("r_f = 8", "r_f: 8")).unwrap_err(), ImportError::InvalidElement("r_f: 8".to_string()));
+        assert!(matches!(parse_combined_params(&raw.replace("2b90bba0", "zz90bba0")), Err(ImportError::InvalidElement(_))));
+        assert_eq!(parse_combined_params(&raw.replace("r_f = 8", "r_f = 7")).unwrap_err(), ImportError::InvalidParameter("r_f = 7".to_string()));
+        let single_row = "[params]\nr_f = 8\nr_p = 57\n[matrix]\n01\n[constants]\n01";
+        assert_eq!(parse_combined_params(single_row).unwrap_err(), ImportError::InvalidParameter("t = 1".to_string()));
+        for alpha in [0, 1, 3] {
+            assert_eq!(parse_combined_params(&raw.replace("alpha = 5", &format!("alpha = {}", alpha))).unwrap_err(),
+                ImportError::InvalidParameter(format!("alpha = {}", alpha)));
+        }
+        assert_eq!(parse_combined_params(&raw.replace("r_p = 57", "r_p = 57\nR_P = 57")).unwrap_err(),
+            ImportError::InvalidParameter("unknown parameter R_P".to_string()));
+        assert_eq!(parse_combined_params(&raw.replace("r_p = 57", "r_p = 57\nr_p = 56")).unwrap_err(),
+            ImportError::InvalidParameter("duplicate parameter r_p".to_string()));
+    }
+}
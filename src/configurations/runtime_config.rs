@@ -0,0 +1,224 @@
+//! This module defines `RuntimeConfig`, which holds the parameters of a Poseidon
+//! configuration as plain data, with the state width only known at runtime, e.g. for
+//! selecting a configuration by name (see the `registry` module).
+
+use ark_ff::Field;
+
+use crate::configurations::poseidon_config::PoseidonConfig;
+use crate::dyn_sponge::{DynPermutation, DynSponge};
+use crate::permutation::{perm, sbox_runtime};
+
+/// The parameters of a Poseidon configuration, with the state width only known at runtime.
+/// 
+/// Besides the round numbers, the matrix and the constants, this carries the flags of
+/// `PoseidonConfig` which change the result of the permutation, so that `perm_dyn` computes
+/// the same permutation as `perm`.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig<F: Field> {
+    pub name: &'static str,
+    pub t: usize,
+    pub r_f: usize,
+    pub r_p: usize,
+    pub alpha: u64,
+    /// See `PoseidonConfig::PARTIAL_SBOX_INDEX`.
+    pub partial_sbox_index: usize,
+    /// See `PoseidonConfig::INVERSE_SBOX`.
+    pub inverse_sbox: bool,
+    /// See `PoseidonConfig::HAS_INITIAL_MDS`.
+    pub has_initial_mds: bool,
+    /// See `PoseidonConfig::ROUND_CONSTANT_WIDTH`.
+    pub round_constant_width: usize,
+    /// See `PoseidonConfig::CONSTANTS_BEFORE_SBOX`.
+    pub constants_before_sbox: bool,
+    pub mds_matrix: Vec<Vec<F>>,
+    pub round_constants: Vec<F>,
+    /// The specialized permutation of a built-in config, or `None` for a config parsed at
+    /// runtime, which is permuted with `perm_dyn` instead (see `permute`). It panics if the
+    /// state does not have `t` elements.
+    pub permutation: Option<fn(&mut [F])>,
+}
+
+impl<F: Field> RuntimeConfig<F> {
+
+    /// Copies the parameters of the config `P` into a `RuntimeConfig` with the given name.
+    /// 
+    /// `CONSTANT_TIME_INVERSION` is not carried over, since it does not change the result,
+    /// but the specialized permutation of the config still honours it.
+    pub fn from_config<const T: usize, P: PoseidonConfig<F, T>>(name: &'static str) -> Self {
+        RuntimeConfig {
+            name,
+            t: T,
+            r_f: P::R_F,
+            r_p: P::R_P,
+            alpha: P::ALPHA,
+            partial_sbox_index: P::PARTIAL_SBOX_INDEX,
+            inverse_sbox: P::INVERSE_SBOX,
+            has_initial_mds: P::HAS_INITIAL_MDS,
+            round_constant_width: P::ROUND_CONSTANT_WIDTH,
+            constants_before_sbox: P::CONSTANTS_BEFORE_SBOX,
+            mds_matrix: P::mds_matrix().iter().map(|row| row.to_vec()).collect(),
+            round_constants: P::round_constants().to_vec(),
+            permutation: Some(|state: &mut [F]| {
+                let state: &mut [F; T] = state.try_into().expect("state size must be t");
+                perm::<F, T, P>(state);
+            }),
+        }
+    }
+
+    /// Applies the permutation of the config to the state, with the specialized permutation
+    /// if there is one, and with `perm_dyn` otherwise. Panics if the state does not have `t`
+    /// elements.
+    pub fn permute(&self, state: &mut [F]) {
+        match self.permutation {
+            Some(permutation) => permutation(state),
+            None => perm_dyn(state, self),
+        }
+    }
+
+    /// Hashes the input with a rate-1 sponge with an all-zero initial state, as
+    /// `x5_254_3::hash` does for `x5_254_3`.
+    pub fn hash(&self, input: &[F]) -> F {
+        let permutation: DynPermutation<F> = match self.permutation {
+            Some(permutation) => Box::new(permutation),
+            None => {
+                let config = self.clone();
+                Box::new(move |state: &mut [F]| perm_dyn(state, &config))
+            }
+        };
+        let mut sponge = DynSponge::new(1, permutation, vec![F::zero(); self.t]);
+        sponge.hash(input, 1)[0]
+    }
+}
+
+/// Performs the Poseidon permutation on a state whose width is only known at runtime, with
+/// the parameters of a `RuntimeConfig`, e.g. in combination with a `DynSponge`.
+/// 
+/// This honours the flags carried by the config, so it computes the same permutation as
+/// `perm` for the config it was created from, but it is slower, since the matrix and the
+/// state are not fixed-size arrays. Panics if the state does not have `config.t` elements,
+/// or if there are not exactly `t * (r_f + r_p)` round constants.
+pub fn perm_dyn<F: Field>(state: &mut [F], config: &RuntimeConfig<F>) {
+    assert_eq!(state.len(), config.t, "The state must have t elements");
+    assert_eq!(config.round_constants.len(), config.t * (config.r_f + config.r_p), "The number of round constants should be t * (r_f + r_p)");
+    let sbox = |x: &F| if config.inverse_sbox {
+        x.inverse().unwrap_or(F::zero())
+    } else {
+        sbox_runtime(x, config.alpha)
+    };
+    let add_round_constants = |state: &mut [F], constants: &[F]| {
+        for (word, constant) in state.iter_mut().zip(constants).take(config.round_constant_width) {
+            *word += constant;
+        }
+    };
+    let mut next = vec![F::zero(); config.t];
+    let mut mds = |state: &mut [F]| {
+        for (result, row) in next.iter_mut().zip(config.mds_matrix.iter()) {
+            *result = row.iter().zip(state.iter()).map(|(m, x)| *m * x).sum();
+        }
+        state.copy_from_slice(&next);
+    };
+
+    if config.has_initial_mds {
+        mds(state);
+    }
+    let half_full_rounds = config.r_f / 2;
+    for (round, constants) in config.round_constants.chunks(config.t).enumerate() {
+        if config.constants_before_sbox {
+            add_round_constants(state, constants);
+        }
+        if round < half_full_rounds || round >= half_full_rounds + config.r_p {
+            for word in state.iter_mut() {
+                *word = sbox(word);
+            }
+        } else {
+            state[config.partial_sbox_index] = sbox(&state[config.partial_sbox_index]);
+        }
+        if !config.constants_before_sbox {
+            add_round_constants(state, constants);
+        }
+        mds(state);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::Zero;
+    use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+    use crate::configurations::registry::get_config;
+
+    /// The x5_254_3 parameters with all flags changed from their defaults.
+    struct FlaggedConfig;
+    impl PoseidonConfig<Fr, 3> for FlaggedConfig {
+        const R_F: usize = 8;
+        const R_P: usize = 57;
+        const PARTIAL_SBOX_INDEX: usize = 2;
+        const INVERSE_SBOX: bool = true;
+        const HAS_INITIAL_MDS: bool = true;
+        const ROUND_CONSTANT_WIDTH: usize = 1;
+        const CONSTANTS_BEFORE_SBOX: bool = false;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            ConfigX5_254_3::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            ConfigX5_254_3::round_constants()
+        }
+    }
+
+    #[test]
+    fn test_from_config_carries_flags() {
+        let config = RuntimeConfig::from_config::<3, FlaggedConfig>("flagged");
+        assert_eq!(config.partial_sbox_index, 2);
+        assert!(config.inverse_sbox);
+        assert!(config.has_initial_mds);
+        assert_eq!(config.round_constant_width, 1);
+        assert!(!config.constants_before_sbox);
+
+        let standard = RuntimeConfig::from_config::<3, ConfigX5_254_3>("x5_254_3");
+        assert_eq!(standard.partial_sbox_index, 0);
+        assert!(!standard.inverse_sbox);
+        assert!(!standard.has_initial_mds);
+        assert_eq!(standard.round_constant_width, 3);
+        assert!(standard.constants_before_sbox);
+    }
+
+    #[test]
+    fn test_perm_dyn() {
+        let config = get_config("x5_254_3").unwrap();
+        for n in 0..5u64 {
+            let mut expected = [Fr::from(n), Fr::from(2 * n), Fr::from(3 * n + 1)];
+            let mut state = expected.to_vec();
+            perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+            perm_dyn(&mut state, &config);
+            assert_eq!(state, expected.to_vec());
+        }
+
+        let mut sponge = DynSponge::new(1, Box::new(move |state: &mut [Fr]| perm_dyn(state, &config)), vec![Fr::zero(); 3]);
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        assert_eq!(sponge.hash(&input, 1), crate::x5_254_3::hash(&input).to_vec());
+    }
+
+    #[test]
+    fn test_perm_dyn_honours_flags() {
+        let config = RuntimeConfig::from_config::<3, FlaggedConfig>("flagged");
+        for n in 0..5u64 {
+            let mut expected = [Fr::from(n), Fr::from(2 * n), Fr::from(3 * n + 1)];
+            let mut state = expected.to_vec();
+            perm::<Fr, 3, FlaggedConfig>(&mut expected);
+            perm_dyn(&mut state, &config);
+            assert_eq!(state, expected.to_vec());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The number of round constants should be t * (r_f + r_p)")]
+    fn test_perm_dyn_checks_round_constants() {
+        let mut config = get_config("x5_254_3").unwrap();
+        config.round_constants.pop();
+        perm_dyn(&mut [Fr::zero(); 3], &config);
+    }
+}
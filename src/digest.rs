@@ -0,0 +1,118 @@
+//! This file defines the `Digest` type, a wrapper around the output of a hash function over
+//! the BN254 scalar field.
+//!
+//! Wrapping a digest in its own type keeps it from being mixed up with other field elements,
+//! e.g. by accidentally hashing it as input. The wrapped element is still accessible, and a
+//! `Digest` converts into an `Fr` where this is intended.
+
+use std::fmt;
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+
+use crate::x5_254_3::digest_to_bytes;
+use crate::Endianness;
+
+/// The digest of a hash function over the BN254 scalar field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Digest(pub Fr);
+
+impl Digest {
+
+    /// Serializes the digest to its canonical 32 big-endian bytes.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&digest_to_bytes(&self.0, Endianness::Big));
+        bytes
+    }
+
+    /// Encodes the digest as 64 lowercase hex digits, in big-endian order as in the
+    /// parameter files.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+}
+
+/// The errors which can occur when reconstructing a digest from bytes.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DigestError {
+    /// The input does not have 32 bytes, but the given number.
+    WrongLength(usize),
+    /// The bytes encode an integer which is not smaller than the modulus.
+    OutOfRange,
+}
+
+/// Reconstructs a digest from its 32 big-endian bytes, as returned by `to_bytes`. Only the
+/// canonical encoding is accepted, i.e. the encoded integer must be smaller than the modulus.
+impl TryFrom<&[u8]> for Digest {
+    type Error = DigestError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != 32 {
+            return Err(DigestError::WrongLength(bytes.len()));
+        }
+        let digest = Digest(Fr::from_be_bytes_mod_order(bytes));
+        // The bytes are canonical exactly if no reduction took place.
+        if digest.to_bytes() != bytes {
+            return Err(DigestError::OutOfRange);
+        }
+        Ok(digest)
+    }
+}
+
+impl From<Fr> for Digest {
+    fn from(element: Fr) -> Self {
+        Digest(element)
+    }
+}
+
+impl From<Digest> for Fr {
+    fn from(digest: Digest) -> Self {
+        digest.0
+    }
+}
+
+/// Displays the digest as its hex encoding, see `to_hex`.
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::BigInteger;
+
+    #[test]
+    fn test_conversions() {
+        let element = crate::x5_254_3::hash(&[Fr::from(1), Fr::from(2)])[0];
+        let digest = Digest::from(element);
+        assert_eq!(Fr::from(digest), element);
+        assert_eq!(digest.to_bytes(), crate::x5_254_3::hash_fixed_output_bytes(&[Fr::from(1), Fr::from(2)]));
+        assert_eq!(Fr::from_be_bytes_mod_order(&digest.to_bytes()), element);
+        assert_eq!(hex::decode(digest.to_hex()).unwrap(), digest.to_bytes());
+    }
+
+    #[test]
+    fn test_try_from_bytes() {
+        let digest = Digest(crate::x5_254_3::hash(&[Fr::from(1), Fr::from(2)])[0]);
+        assert_eq!(Digest::try_from(&digest.to_bytes()[..]), Ok(digest));
+        assert_eq!(Digest::try_from(&[0u8; 32][..]), Ok(Digest(Fr::from(0))));
+
+        assert_eq!(Digest::try_from(&digest.to_bytes()[1..]), Err(DigestError::WrongLength(31)));
+        assert_eq!(Digest::try_from(&[0u8; 33][..]), Err(DigestError::WrongLength(33)));
+
+        let modulus = Fr::MODULUS.to_bytes_be();
+        assert_eq!(Digest::try_from(&modulus[..]), Err(DigestError::OutOfRange));
+        assert_eq!(Digest::try_from(&[0xff; 32][..]), Err(DigestError::OutOfRange));
+    }
+
+    #[test]
+    fn test_display() {
+        let digest = Digest(Fr::from(0x1234));
+        assert_eq!(digest.to_hex().len(), 64);
+        assert_eq!(digest.to_string(), format!("{}1234", "0".repeat(60)));
+        assert_eq!(digest.to_string(), digest.to_hex());
+    }
+}
@@ -0,0 +1,137 @@
+//! This file defines a Sponge construction whose rate and capacity are chosen at runtime.
+//!
+//! The `Sponge` in `sponge.rs` takes the rate and the state size as const generics, which
+//! requires them to be known at compile time. A `DynSponge` instead stores its state as a
+//! `Vec<F>`, and calls its permutation through a boxed closure. This is slower, but allows
+//! the rate to be read e.g. from a configuration file.
+//!
+//! Apart from that, a `DynSponge` behaves exactly as a `Sponge` with the same rate, state
+//! size and initial state: full blocks are permuted as soon as they have been absorbed, and
+//! a trailing partial block is padded with zeroes when squeezing.
+
+use ark_ff::Field;
+
+use crate::sponge::Permutation;
+
+/// A permutation acting on a state whose size is only known at runtime. It is required to be
+/// `Send + Sync`, so that a `DynSponge` can be moved to and shared with other threads.
+pub type DynPermutation<F> = Box<dyn Fn(&mut [F]) + Send + Sync>;
+
+/// A Sponge with a runtime rate and capacity, see the module documentation.
+pub struct DynSponge<F: Field> {
+    state: Vec<F>,
+    rate: usize,
+    absorb_pos: usize,
+    permutation: DynPermutation<F>,
+}
+
+impl<F: Field> DynSponge<F> {
+
+    /// Creates a new DynSponge with the given rate, permutation and initial state. The
+    /// capacity is the remaining `start_state.len() - rate` elements.
+    pub fn new(rate: usize, permutation: DynPermutation<F>, start_state: Vec<F>) -> Self {
+        assert!(rate > 0 && rate <= start_state.len(), "rate must be positive and at most the state size");
+        DynSponge {
+            state: start_state,
+            rate,
+            absorb_pos: 0,
+            permutation,
+        }
+    }
+
+    /// Creates a new DynSponge using the permutation `P` for states of size `N`, with the
+    /// given rate and an all-zero initial state.
+    pub fn from_permutation<P: Permutation<F, N> + 'static, const N: usize>(rate: usize) -> Self {
+        let permutation: DynPermutation<F> = Box::new(|state: &mut [F]| {
+            let state: &mut [F; N] = state.try_into().expect("state size must be N");
+            P::apply(state);
+        });
+        Self::new(rate, permutation, vec![F::zero(); N])
+    }
+
+    /// The rate of the sponge.
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    /// The capacity of the sponge.
+    pub fn capacity(&self) -> usize {
+        self.state.len() - self.rate
+    }
+
+    /// Absorbs any number of elements into the sponge state, as `Sponge::absorb_slice`.
+    pub fn absorb_slice(&mut self, input: &[F]) {
+        for x in input {
+            self.state[self.absorb_pos] += x;
+            self.absorb_pos += 1;
+            if self.absorb_pos == self.rate {
+                (self.permutation)(&mut self.state);
+                self.absorb_pos = 0;
+            }
+        }
+    }
+
+    /// Squeezes `rate` elements from the sponge state, as `Sponge::squeeze`.
+    pub fn squeeze(&mut self) -> Vec<F> {
+        let output = self.squeeze_final();
+        (self.permutation)(&mut self.state);
+        output
+    }
+
+    /// Squeezes `rate` elements from the sponge state without permuting it afterwards, as
+    /// `Sponge::squeeze_final`.
+    pub fn squeeze_final(&mut self) -> Vec<F> {
+        if self.absorb_pos > 0 {
+            (self.permutation)(&mut self.state);
+            self.absorb_pos = 0;
+        }
+        self.state[..self.rate].to_vec()
+    }
+
+    /// Absorbs the input and squeezes `out_len` elements, as `Sponge::hash`.
+    pub fn hash(&mut self, input: &[F], out_len: usize) -> Vec<F> {
+        self.absorb_slice(input);
+        let mut output = Vec::with_capacity(out_len);
+        while output.len() + self.rate < out_len {
+            output.extend(self.squeeze());
+        }
+        if output.len() < out_len {
+            output.extend(self.squeeze_final());
+        }
+        output.truncate(out_len);
+        output
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use crate::sponge::Sponge;
+    use crate::x5_254_3::PermX5_254_3;
+
+    #[test]
+    fn test_matches_sponge() {
+        let input = crate::field_vec_from_u64(&[0, 1, 2, 3, 4]);
+        let mut dyn_sponge = DynSponge::<Fr>::from_permutation::<PermX5_254_3, 3>(2);
+        assert_eq!(dyn_sponge.rate(), 2);
+        assert_eq!(dyn_sponge.capacity(), 1);
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::from(0); 3]);
+        assert_eq!(dyn_sponge.hash(&input, 5), sponge.hash(&input, 5));
+    }
+
+    #[test]
+    fn test_custom_permutation() {
+        // A rotation by one, on a state of size 4 with rate 3.
+        let mut sponge = DynSponge::<Fr>::new(3, Box::new(|state: &mut [Fr]| state.rotate_left(1)), vec![Fr::from(0); 4]);
+        sponge.absorb_slice(&[Fr::from(1), Fr::from(2)]);
+        assert_eq!(sponge.squeeze(), vec![Fr::from(2), Fr::from(0), Fr::from(0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rate_too_large() {
+        DynSponge::<Fr>::new(4, Box::new(|_: &mut [Fr]| {}), vec![Fr::from(0); 3]);
+    }
+}
@@ -0,0 +1,99 @@
+//! This file defines the `FieldBytes` trait, which is how the library converts bytes into
+//! field elements, e.g. when parsing the hex-encoded parameter files.
+//!
+//! The `Field` trait of arkworks only offers `from_random_bytes`, whose behavior depends on
+//! the field: it reads as many bytes as the field needs, and masks away the bits above the
+//! modulus size, so a too large value may be silently truncated into a valid element. The
+//! `FieldBytes` trait makes the conversion explicit instead.
+//!
+//! The trait is implemented per field: for the prime fields of this library with the
+//! `impl_field_bytes!` macro, which rejects non-canonical encodings. Other fields, e.g. the
+//! extension fields of a custom config, implement `from_le_bytes_canonical` themselves with
+//! whatever byte mapping suits them.
+
+use ark_ff::{BigInteger, Field, PrimeField};
+
+/// A field whose elements can be read from bytes.
+pub trait FieldBytes: Field {
+    /// Reads an element from its little-endian byte encoding. Returns `None` if the bytes do
+    /// not encode an element in canonical form, i.e. if the encoded integer is not smaller
+    /// than the modulus. Trailing zero bytes are allowed.
+    fn from_le_bytes_canonical(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Reads an element of a prime field from its little-endian byte encoding as
+/// `FieldBytes::from_le_bytes_canonical`: the integer is reduced modulo the order, and
+/// rejected if a reduction took place. This is the implementation of `impl_field_bytes!`.
+pub fn prime_field_from_le_bytes_canonical<F: PrimeField>(bytes: &[u8]) -> Option<F> {
+    let element = F::from_le_bytes_mod_order(bytes);
+    let canonical = element.into_bigint().to_bytes_le();
+    (without_trailing_zeroes(&canonical) == without_trailing_zeroes(bytes)).then_some(element)
+}
+
+/// Implements `FieldBytes` for the given prime fields with
+/// `prime_field_from_le_bytes_canonical`, e.g. `impl_field_bytes!(ark_bn254::Fr);`.
+#[macro_export]
+macro_rules! impl_field_bytes {
+    ($($field:ty),+ $(,)?) => {
+        $(
+            impl $crate::field_bytes::FieldBytes for $field {
+                fn from_le_bytes_canonical(bytes: &[u8]) -> Option<Self> {
+                    $crate::field_bytes::prime_field_from_le_bytes_canonical(bytes)
+                }
+            }
+        )+
+    };
+}
+
+impl_field_bytes!(ark_bn254::Fr, ark_bn254::Fq);
+#[cfg(feature = "bls12_381")]
+impl_field_bytes!(ark_bls12_381::Fr);
+
+/// Strips the most significant zero bytes from a little-endian byte string.
+fn without_trailing_zeroes(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    &bytes[..len]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_prime_field() {
+        assert_eq!(Fr::from_le_bytes_canonical(&[]), Some(Fr::from(0)));
+        assert_eq!(Fr::from_le_bytes_canonical(&[3]), Some(Fr::from(3)));
+        assert_eq!(Fr::from_le_bytes_canonical(&[1, 1, 0, 0]), Some(Fr::from(257)));
+        let mut bytes = (-Fr::from(1)).into_bigint().to_bytes_le();
+        assert_eq!(Fr::from_le_bytes_canonical(&bytes), Some(-Fr::from(1)));
+        bytes[0] += 1;
+        assert_eq!(Fr::from_le_bytes_canonical(&bytes), None);
+        assert_eq!(Fr::from_le_bytes_canonical(&[0xff; 32]), None);
+        assert_eq!(Fr::from_le_bytes_canonical(&[0xff; 40]), None);
+        assert_eq!(ark_bn254::Fq::from_le_bytes_canonical(&[3]), Some(ark_bn254::Fq::from(3)));
+    }
+
+    /// An extension field reading its two coefficients from two 32-byte halves.
+    impl FieldBytes for ark_bn254::Fq2 {
+        fn from_le_bytes_canonical(bytes: &[u8]) -> Option<Self> {
+            if bytes.len() != 64 {
+                return None;
+            }
+            let c0 = ark_bn254::Fq::from_le_bytes_canonical(&bytes[..32])?;
+            let c1 = ark_bn254::Fq::from_le_bytes_canonical(&bytes[32..])?;
+            Some(ark_bn254::Fq2::new(c0, c1))
+        }
+    }
+
+    #[test]
+    fn test_extension_field() {
+        let mut bytes = [0u8; 64];
+        bytes[0] = 1;
+        bytes[32] = 2;
+        let expected = ark_bn254::Fq2::new(ark_bn254::Fq::from(1), ark_bn254::Fq::from(2));
+        assert_eq!(ark_bn254::Fq2::from_le_bytes_canonical(&bytes), Some(expected));
+        assert_eq!(ark_bn254::Fq2::from_le_bytes_canonical(&bytes[..32]), None);
+    }
+}
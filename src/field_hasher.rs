@@ -0,0 +1,103 @@
+//! This file defines the `FieldHasher` trait, which abstracts over hash functions mapping a
+//! sequence of field elements to a single field element.
+//!
+//! Code which only needs "some field hash" can be written generically over `FieldHasher`,
+//! and then be used with any of the Poseidon configurations of this library, or with another
+//! hash function implementing the trait.
+//!
+//! The trait is implemented for the configurations which have a sponge hash function in this
+//! library, i.e. `ConfigX5_254_3` (see `x5_254_3::hash`) and `ConfigX5_254_6` (see
+//! `x5_254_6::hash`). The other configurations only provide the permutation, and fixed-arity
+//! hashes of their width are available in the `compat` module.
+//!
+//! For fixed-arity uses such as binary Merkle trees, `hash_two_to_one` compresses two field
+//! elements with a single permutation of any configuration.
+
+use ark_bn254::Fr;
+use ark_ff::Field;
+
+use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+use crate::configurations::poseidon_config::PoseidonConfig;
+use crate::permutation::perm;
+
+/// A hash function mapping any number of field elements to a single field element.
+pub trait FieldHasher<F: Field> {
+    fn hash(input: &[F]) -> F;
+}
+
+/// The x5_254_3 hash function, see `x5_254_3::hash`.
+impl FieldHasher<Fr> for ConfigX5_254_3 {
+    fn hash(input: &[Fr]) -> Fr {
+        crate::x5_254_3::hash(input)[0]
+    }
+}
+
+/// The x5_254_6 hash function, see `x5_254_6::hash`.
+impl FieldHasher<Fr> for ConfigX5_254_6 {
+    fn hash(input: &[Fr]) -> Fr {
+        crate::x5_254_6::hash(input)[0]
+    }
+}
+
+/// Compresses two field elements into one, with a single permutation of the configuration `C`.
+/// 
+/// This is the sponge with rate `T - 1`, a zero initial state and a single squeezed element:
+/// `a` and `b` are absorbed into the first two elements of the rate, the state is permuted,
+/// and the first element is returned. Both inputs must fit in the rate, so `T` must be at
+/// least 3, which is checked at compile time.
+pub fn hash_two_to_one<F: Field, C: PoseidonConfig<F, T>, const T: usize>(a: F, b: F) -> F {
+    const { assert!(T >= 3, "hash_two_to_one requires a rate of at least 2") };
+    let mut state = [F::zero(); T];
+    state[0] = a;
+    state[1] = b;
+    perm::<F, T, C>(&mut state);
+    state[0]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A (very insecure) hash function summing its input, to show that backends can be swapped.
+    struct SumHasher;
+    impl FieldHasher<Fr> for SumHasher {
+        fn hash(input: &[Fr]) -> Fr {
+            input.iter().sum()
+        }
+    }
+
+    /// Hashes the leaves pairwise into a root, for any hash function.
+    fn root<F: Field, H: FieldHasher<F>>(leaves: &[F]) -> F {
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level.chunks(2).map(H::hash).collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn test_hash_two_to_one() {
+        use crate::sponge::Sponge;
+        use crate::x5_254_3::PermX5_254_3;
+        let (a, b) = (Fr::from(1), Fr::from(2));
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::from(0); 3]);
+        assert_eq!(hash_two_to_one::<Fr, ConfigX5_254_3, 3>(a, b), sponge.hash(&[a, b], 1)[0]);
+        assert_ne!(hash_two_to_one::<Fr, ConfigX5_254_3, 3>(a, b), hash_two_to_one::<Fr, ConfigX5_254_3, 3>(b, a));
+    }
+
+    #[test]
+    fn test_generic_over_hasher() {
+        let leaves = crate::field_vec_from_u64(&[1, 2, 3, 4]);
+        assert_eq!(root::<Fr, SumHasher>(&leaves), Fr::from(10));
+
+        let left = crate::x5_254_3::hash(&[Fr::from(1), Fr::from(2)])[0];
+        let right = crate::x5_254_3::hash(&[Fr::from(3), Fr::from(4)])[0];
+        assert_eq!(root::<Fr, ConfigX5_254_3>(&leaves), crate::x5_254_3::hash(&[left, right])[0]);
+
+        let left = crate::x5_254_6::hash(&[Fr::from(1), Fr::from(2)])[0];
+        let right = crate::x5_254_6::hash(&[Fr::from(3), Fr::from(4)])[0];
+        assert_eq!(root::<Fr, ConfigX5_254_6>(&leaves), crate::x5_254_6::hash(&[left, right])[0]);
+    }
+}
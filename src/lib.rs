@@ -1,27 +1,130 @@
 //! A library implementing the Poseidon hash function family.
 //! 
 //! At the moment, only one concrete Poseidon hash function is implemented, namely the
-//! x5_254_3 hash function.
+//! x5_254_3 hash function. With the `bls12_381` feature, the configuration `ConfigX5_255_3`
+//! provides the corresponding permutation over the BLS12-381 scalar field. With the `ark-crh`
+//! feature, the `ark_crh` module implements arkworks' CRH traits, for use in arkworks' Merkle
+//! trees.
 //! 
 //! In order to create another member of the Poseidon family, one needs to define a struct
-//! implementing the `PoseidonConfig` trait.
+//! implementing the `PoseidonConfig` trait. The `poseidon_config` attribute generates such an
+//! implementation from files holding the round constants and the MDS matrix.
 //! 
 //! Note that this library is not cryptographically secure (since it e.g. does not consider
 //! side channel attacks), and it should thus not be used for cryptographic purposes.
+//! 
+//! Apart from the plain `Sponge` construction, the library provides a `SafeSponge` following
+//! the SAFE (Sponge API for Field Elements) specification.
 
 mod configurations;
-mod sponge;
+pub mod sponge;
+pub mod dyn_sponge;
+pub mod sponge_pool;
+pub mod safe_sponge;
+pub mod transcript;
 mod permutation;
+pub mod field_hasher;
+pub mod digest;
+pub mod field_bytes;
+pub mod std_hasher;
+pub mod compat;
+pub mod tree;
+#[cfg(feature = "ark-crh")]
+pub mod ark_crh;
+#[cfg(test)]
+mod test_fields;
+
+pub use poseidonito_macros::poseidon_config;
+
+// The `configurations` and `permutation` modules are internal, and only the items below are
+// part of the public API.
+pub use crate::configurations::poseidon_config::{
+    PoseidonConfig, batch_inverse, config_fingerprint, exponent_limbs, exponent_limbs_u128,
+    inverse_constant_time, is_valid_round_configuration, is_valid_sbox_exponent, num_round_constants,
+    satisfies_round_bounds, sbox_mults, sbox_pow5,
+};
+pub use crate::configurations::config_x5_254_2::ConfigX5_254_2;
+pub use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+pub use crate::configurations::config_x5_254_3::static_config::StaticConfigX5_254_3;
+pub use crate::configurations::config_x5_254_4::ConfigX5_254_4;
+pub use crate::configurations::config_x5_254_5::ConfigX5_254_5;
+pub use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+#[cfg(feature = "bls12_381")]
+pub use crate::configurations::config_x5_255_3::ConfigX5_255_3;
+pub use crate::configurations::grain_lfsr::{GrainLfsr, generate_parameters, generate_round_constants};
+pub use crate::configurations::optimized_constants::{
+    ImportError, OptimizedConstants, SparseMatrix, derive_optimized_constants, import_neptune_constants,
+};
+pub use crate::configurations::parse_matrix_flat;
+pub use crate::configurations::registry::{
+    CONFIG_NAMES, PARSED_CONFIG_NAME, get_config, parse_combined_params,
+};
+pub use crate::configurations::runtime_config::{RuntimeConfig, perm_dyn};
+pub use crate::permutation::{
+    LinearLayerPath, PoseidonPermutation, apply_full_rounds, apply_partial_rounds, matrix_vector_mul,
+    matrix_vector_mul_transposed, perm, perm_auto, perm_optimized, perm_trace,
+    perm_trace_circom, permutation_fn, transpose_matrix,
+};
+
+use crate::sponge::Sponge;
+
+// Allows the code generated by `poseidon_config` to refer to `::poseidonito` inside this crate.
+extern crate self as poseidonito;
+
+/// Items used by the code generated by `poseidon_config`. This is not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use ark_bn254;
+    pub use once_cell::sync::Lazy;
+    pub use crate::configurations::poseidon_config::{PoseidonConfig, num_round_constants};
+    pub use crate::configurations::config_creation_helper_functions::{parse_constants, parse_matrix_rows};
+}
+
+/// The errors which can occur when hashing with the fallible hash functions.
+#[derive(Debug, PartialEq, Eq)]
+pub enum HashError {
+    /// The input element at the given index is not in canonical form.
+    NonCanonicalInput(usize),
+}
+
+/// Converts integers into elements of the BN254 scalar field, e.g. for building hash inputs.
+pub fn field_vec_from_u64(xs: &[u64]) -> Vec<ark_bn254::Fr> {
+    xs.iter().map(|x| ark_bn254::Fr::from(*x)).collect()
+}
+
+/// Hashes the input with a sponge of rate `RATE` over the Poseidon permutation of the
+/// configuration `C` with state size `T`, with an all-zero initial state, and returns a single
+/// output element. With `ConfigX5_254_3` and `RATE = 1`, this is `x5_254_3::hash`.
+/// 
+/// The rate must be strictly smaller than `T`, so that the sponge has a nonzero capacity,
+/// and this is checked at compile time.
+pub fn poseidon_hash<F: ark_ff::Field, C: PoseidonConfig<F, T>, const RATE: usize, const T: usize>(input: &[F]) -> F {
+    const { assert!(RATE >= 1 && RATE < T, "RATE must be positive and smaller than T") };
+    let mut sponge = Sponge::<F, PoseidonPermutation<C>, RATE, T>::new([F::zero(); T]);
+    sponge.hash(input, 1)[0]
+}
+
+/// The byte order used when serializing a digest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant byte first, as used by e.g. arkworks' canonical serialization.
+    Little,
+    /// Most significant byte first, as used by e.g. the hex strings of the parameter files.
+    Big,
+}
 
 pub mod x5_254_3 {
-    use ark_ff::{Zero};
+    use ark_ff::{Zero, PrimeField, BigInteger};
     use ark_bn254::Fr;
     use crate::configurations::config_x5_254_3::ConfigX5_254_3;
     use crate::permutation::perm;
     use crate::sponge::{Permutation, Sponge};
+    use crate::transcript::pack_bytes;
+    use crate::{Endianness, HashError};
 
-    struct Perm_x5_254_3;
-    impl Permutation<Fr, 3> for Perm_x5_254_3 {
+    /// The x5_254_3 Poseidon permutation, for use with the sponge constructions.
+    pub struct PermX5_254_3;
+    impl Permutation<Fr, 3> for PermX5_254_3 {
         fn apply(state: &mut [Fr; 3]) {
             perm::<Fr, 3, ConfigX5_254_3>(state);
         }
@@ -31,11 +134,344 @@ pub mod x5_254_3 {
         // We probably want something other than all zeroes as the initial state.
         // However, I have not yet found any reference value, so all zeroes will be
         // used as a placeholder for now.
-        let mut sponge = Sponge::<Fr, Perm_x5_254_3, 1, 3>::new([Fr::zero(); 3]);
-        for i in input {
-            sponge.absorb(&[*i]);
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        [sponge.hash(input, 1)[0]]
+    }
+
+    /// Hashes the input as `hash`, which may be given as any contiguous container of field
+    /// elements, e.g. a `Vec<Fr>`, an array or a slice (or a reference to any of these).
+    pub fn hash_any(input: impl AsRef<[ark_bn254::Fr]>) -> [ark_bn254::Fr; 1] {
+        hash(input.as_ref())
+    }
+
+    /// Hashes the elements of an iterator as `hash`, absorbing each element as it is produced,
+    /// so that a long input never needs to be collected into memory.
+    pub fn hash_iter(input: impl IntoIterator<Item = Fr>) -> Fr {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        for x in input {
+            sponge.absorb_single(x);
+        }
+        sponge.squeeze_final()[0]
+    }
+
+    /// Hashes the input into `M` output elements, returned as an array, which avoids any
+    /// allocation when the output size is known at compile time. The output is the same as
+    /// for `Sponge::hash` with `out_len = M`.
+    pub fn hash_to_array<const M: usize>(input: &[Fr]) -> [Fr; M] {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(input);
+        let mut output = [Fr::zero(); M];
+        for (i, element) in output.iter_mut().enumerate() {
+            *element = if i + 1 < M { sponge.squeeze()[0] } else { sponge.squeeze_final()[0] };
+        }
+        output
+    }
+
+    /// Hashes the input as `hash`, and returns the digest together with the sponge, so that
+    /// more elements can be absorbed after reading it, e.g. for incremental transcript
+    /// commitments.
+    /// 
+    /// The digest is read with `squeeze`, so unlike in `hash`, the state is permuted after
+    /// reading it, and the elements absorbed next are not mixed with a revealed state.
+    pub fn hash_and_continue(input: &[Fr]) -> (Fr, Sponge<Fr, PermX5_254_3, 1, 3>) {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(input);
+        let digest = sponge.squeeze()[0];
+        (digest, sponge)
+    }
+
+    /// Hashes the input with a sponge of the given rate, with an all-zero initial state.
+    /// 
+    /// The valid rates are 1 and 2, which is checked at compile time. With rate 1, this is
+    /// the same as `hash`. Rate 2 absorbs two elements per permutation, at the cost of only
+    /// a single capacity element.
+    /// 
+    /// Note that rate 2 is not a drop-in replacement for variable-length inputs: a trailing
+    /// partial block is padded with zeroes (see the padding note of `Sponge`), so e.g.
+    /// `hash_rate::<2>(&[a])` equals `hash_rate::<2>(&[a, 0])`. Fix or encode the input
+    /// length if such inputs must be distinguished.
+    pub fn hash_rate<const RATE: usize>(input: &[Fr]) -> Fr {
+        const { assert!(crate::sponge::is_valid_rate(RATE, 3), "RATE must be 1 or 2") };
+        let mut sponge = Sponge::<Fr, PermX5_254_3, RATE, 3>::new([Fr::zero(); 3]);
+        sponge.hash(input, 1)[0]
+    }
+
+    /// Compresses exactly two elements as `hash_rate::<2>(&[a, b])`, which is the 2-to-1
+    /// compression of e.g. binary Merkle trees.
+    /// 
+    /// This is a specialized path for this hot operation: the state `[a, b, 0]` is constructed
+    /// directly and permuted once (see `field_hasher::hash_two_to_one`), without the absorb
+    /// loop and buffering of the sponge.
+    /// 
+    /// Note that this is not circom's `Poseidon(2)`: the inputs are in the rate at the front of
+    /// the state, and the capacity is last, whereas `compat::hash_two` permutes `[0, a, b]`.
+    /// Use `compat::hash_two` for digests which match circomlib.
+    #[inline]
+    pub fn compress_two(a: Fr, b: Fr) -> Fr {
+        crate::field_hasher::hash_two_to_one::<Fr, ConfigX5_254_3, 3>(a, b)
+    }
+
+    /// Hashes a fixed number of elements as `hash`, without any heap allocation, e.g. for
+    /// zk-VM guests without an allocator.
+    /// 
+    /// Unlike `hash`, this uses `StaticConfigX5_254_3`, whose parameters are compile-time
+    /// arrays (checked against the parameters of `ConfigX5_254_3` in its tests), so that no
+    /// constants are parsed (and allocated) on first use either. Note that this is not a
+    /// `no_std` path: the crate itself still depends on `std`.
+    pub fn hash_fixed_noalloc<const L: usize>(input: &[Fr; L]) -> Fr {
+        use crate::configurations::config_x5_254_3::static_config::StaticConfigX5_254_3;
+        use crate::permutation::PoseidonPermutation;
+        let mut sponge = Sponge::<Fr, PoseidonPermutation<StaticConfigX5_254_3>, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(input);
+        sponge.squeeze_final()[0]
+    }
+
+    /// Hashes the input using the given buffer as the sponge state, and returns the digest.
+    /// 
+    /// This is a low-level API for hot loops, where the caller may reuse the same state
+    /// buffer across many hashes, without any allocation per call. The buffer is reset to the
+    /// initial state before hashing, and holds the final sponge state afterwards. The digest
+    /// is the same as the one returned by `hash`.
+    pub fn hash_in_place(state: &mut [Fr; 3], input: &[Fr]) -> Fr {
+        *state = [Fr::zero(); 3];
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3, _>::from_state_mut(state);
+        sponge.absorb_slice(input);
+        sponge.squeeze_final()[0]
+    }
+
+    /// Aggregates any number of roots (e.g. Merkle roots or other sub-hashes) into a single
+    /// field element.
+    /// 
+    /// The number of roots is used as a domain tag in the last capacity element of the initial
+    /// state, so that the encoding is prefix-free, and the aggregate of one or more roots is
+    /// separated from `hash` of any elements.
+    pub fn hash_roots(roots: &[Fr]) -> Fr {
+        let mut start_state = [Fr::zero(); 3];
+        start_state[2] = Fr::from(roots.len() as u64);
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new(start_state);
+        sponge.hash(roots, 1)[0]
+    }
+
+    /// Hashes two elements with a single permutation of `[separator, a, b]`, and returns the
+    /// first element of the resulting state.
+    /// 
+    /// This is the "domain tag as capacity" convention of circomlib's Poseidon (with t = 3),
+    /// where the capacity lives in `state[0]`, holds the domain tag, and is also where the
+    /// output is read. Note that this differs from `hash`, which uses the first element as
+    /// the rate.
+    pub fn hash_with_separator_byte(separator: u8, input: &[Fr; 2]) -> Fr {
+        let mut state = [Fr::from(separator), input[0], input[1]];
+        PermX5_254_3::apply(&mut state);
+        state[0]
+    }
+
+    /// Computes a Semaphore (v4) identity commitment from the coordinates of the identity's
+    /// Baby Jubjub public key, i.e. `poseidon2([x, y])` in circomlib.
+    /// 
+    /// Semaphore uses circomlib's Poseidon with the domain tag 0 in the capacity, so this is
+    /// `hash_with_separator_byte(0, &[x, y])`.
+    pub fn semaphore_identity_commitment(public_key_x: Fr, public_key_y: Fr) -> Fr {
+        hash_with_separator_byte(0, &[public_key_x, public_key_y])
+    }
+
+    /// Hashes the input, and squeezes further outputs until one satisfies the predicate, e.g.
+    /// for digests which must be even or below a bound. This is rejection sampling: if the
+    /// first output satisfies the predicate, the result is the same as for `hash`.
+    /// 
+    /// If a fraction `q` of all field elements satisfy the predicate, the expected number of
+    /// squeezes (and thus permutations) is `1 / q`. Note that the function does not return
+    /// if the predicate is never satisfied.
+    pub fn hash_with_predicate(input: &[Fr], predicate: impl Fn(&Fr) -> bool) -> Fr {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(input);
+        loop {
+            let output = sponge.squeeze()[0];
+            if predicate(&output) {
+                return output;
+            }
+        }
+    }
+
+    /// Hashes the input under a secret key, for use as a PRF or MAC.
+    /// 
+    /// The key is placed in the capacity (`state[1]`) of an otherwise zero state, and the state
+    /// is permuted once before the input is absorbed, so that the output depends on the key
+    /// even for an empty input. The rest is the same as for `hash`.
+    /// 
+    /// Note that this is an ad-hoc construction: the security relies on the key being a
+    /// uniformly random, secret field element, and on the capacity only being known to the
+    /// key holder. Unlike a `SafeSponge`, the input length is not bound to the output
+    /// (although every element is followed by a permutation, so inputs of different lengths
+    /// are not trivially related), and the implementation is not constant time. Prefer a SAFE
+    /// construction with an IO pattern where possible.
+    pub fn keyed_hash(key: Fr, input: &[Fr]) -> Fr {
+        let mut state = [Fr::zero(), key, Fr::zero()];
+        PermX5_254_3::apply(&mut state);
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new(state);
+        sponge.hash(input, 1)[0]
+    }
+
+    /// Derives a deterministic nonce from a secret and a message, in the spirit of RFC 6979,
+    /// e.g. for signature schemes over the BN254 scalar field.
+    /// 
+    /// The nonce is the `keyed_hash` under the secret of a counter, the length of the message
+    /// and the message. The counter starts at zero, and is only incremented in the
+    /// (negligibly likely) case that the nonce is zero. The same secret and message thus
+    /// always give the same nonce, while the nonce is unpredictable without the secret.
+    /// 
+    /// This is not a vetted construction, so it should not replace the nonce derivation of an
+    /// existing signature scheme without review. Note also that the secret should not be used
+    /// as a `keyed_hash` key for other purposes.
+    pub fn derive_nonce(secret: Fr, message: &[Fr]) -> Fr {
+        let mut input = vec![Fr::zero(), Fr::from(message.len() as u64)];
+        input.extend_from_slice(message);
+        loop {
+            let nonce = keyed_hash(secret, &input);
+            if !nonce.is_zero() {
+                return nonce;
+            }
+            input[0] += Fr::from(1);
+        }
+    }
+
+    /// Hashes the input once for each of the given domains, e.g. for deriving several
+    /// independent outputs from one input in a protocol.
+    /// 
+    /// The input is absorbed only once. The sponge is then branched (cloned) for each domain,
+    /// and the domain is absorbed into the branch before squeezing its output. Hence, the
+    /// output for the domain `d` is `hash` of the input followed by `d`.
+    pub fn hash_multi_domain(input: &[Fr], domains: &[Fr]) -> Vec<Fr> {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(input);
+        domains
+            .iter()
+            .map(|domain| {
+                let mut branch = sponge.clone();
+                branch.absorb_single(*domain);
+                branch.squeeze_final()[0]
+            })
+            .collect()
+    }
+
+    /// Hashes the input under a personalization, similar to BLAKE2's personalization, so
+    /// that one parameter set provides independent hash functions for different domains.
+    /// 
+    /// The personalization bytes are hashed into a tag: their length and the bytes packed
+    /// into field elements (see `pack_bytes`) are hashed with `hash`. The tag is then placed
+    /// in the capacity as the key of `keyed_hash`. Unlike a key, the personalization need not
+    /// be secret.
+    pub fn hash_personalized(personalization: &[u8], input: &[Fr]) -> Fr {
+        let mut encoded = vec![Fr::from(personalization.len() as u64)];
+        encoded.extend(pack_bytes::<Fr>(personalization));
+        keyed_hash(hash(&encoded)[0], input)
+    }
+
+    /// Hashes the input, after checking that all input elements are in canonical form, i.e.
+    /// that their internal representations are smaller than the modulus.
+    /// 
+    /// Elements constructed through the safe arkworks API are always canonical, but elements
+    /// constructed through e.g. `Fr::new_unchecked` need not be. Such elements would still be
+    /// hashed as the element they represent, but may mismatch e.g. a circuit computing the hash
+    /// on the raw representation. Note that `hash` skips this check for speed.
+    pub fn try_hash(input: &[Fr]) -> Result<Fr, HashError> {
+        if let Some(index) = input.iter().position(|x| x.0 >= Fr::MODULUS) {
+            return Err(HashError::NonCanonicalInput(index));
+        }
+        Ok(hash(input)[0])
+    }
+
+    /// Hashes the input as `hash`, after normalizing every input element to its canonical
+    /// form by round-tripping it through its integer representation (`into_bigint`).
+    /// 
+    /// Unlike `try_hash`, this accepts non-canonical elements (e.g. constructed through
+    /// `Fr::new_unchecked`), and hashes them as the element they represent. The round trip
+    /// costs two Montgomery conversions per input element, which is small compared to a
+    /// permutation, but still makes this slower than `hash`, which trusts its input.
+    pub fn hash_normalized(input: &[Fr]) -> Fr {
+        let normalized: Vec<Fr> = input.iter().map(normalize).collect();
+        hash(&normalized)[0]
+    }
+
+    /// Returns the canonical form of an element, see `hash_normalized`.
+    fn normalize(x: &Fr) -> Fr {
+        Fr::from_bigint(x.into_bigint()).expect("into_bigint should return a reduced integer")
+    }
+
+    /// Hashes the input and serializes the single output element to a canonical 32-byte
+    /// big-endian array.
+    /// 
+    /// Note that the output is an element of the BN254 scalar field, whose modulus is
+    /// smaller than 2^254. Hence, the two most significant bits of the first byte are
+    /// always zero, and not all 32-byte arrays are possible outputs.
+    pub fn hash_fixed_output_bytes(input: &[Fr]) -> [u8; 32] {
+        let digest = hash(input)[0];
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&digest_to_bytes(&digest, Endianness::Big));
+        bytes
+    }
+
+    /// Hashes the input and checks whether the digest equals `expected`, e.g. for checking a
+    /// commitment or a nullifier.
+    /// 
+    /// The comparison is constant time: it looks at all bytes of both elements, regardless of
+    /// where they differ. Note however that, as stated in the crate documentation, the rest of
+    /// the library has not been hardened against side channels.
+    pub fn verify_hash(input: &[Fr], expected: Fr) -> bool {
+        let digest = digest_to_bytes(&hash(input)[0], Endianness::Little);
+        let expected = digest_to_bytes(&expected, Endianness::Little);
+        let difference = digest
+            .iter()
+            .zip(expected.iter())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y));
+        std::hint::black_box(difference) == 0
+    }
+
+    /// Serializes a digest to its canonical 32 bytes, in the given byte order.
+    /// 
+    /// Note that the parameter files write field elements as big-endian hex strings, which
+    /// `hex_to_field` reverses before reading them, since arkworks reads (and serializes)
+    /// field elements in little-endian order. A big-endian digest thus matches the hex
+    /// strings, while a little-endian digest matches arkworks' `CanonicalSerialize`.
+    pub fn digest_to_bytes(d: &Fr, endian: Endianness) -> Vec<u8> {
+        let bigint = d.into_bigint();
+        match endian {
+            Endianness::Little => bigint.to_bytes_le(),
+            Endianness::Big => bigint.to_bytes_be(),
         }
-        sponge.squeeze()
+    }
+}
+
+/// Hashing with the x5_254_6 configuration, whose rate of 5 fits 5-element payloads into a
+/// single permutation.
+pub mod x5_254_6 {
+    use ark_ff::Zero;
+    use ark_bn254::Fr;
+    use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+    use crate::permutation::PoseidonPermutation;
+    use crate::sponge::Sponge;
+
+    /// Hashes the input with a sponge of rate 5, whose capacity is initialized to the input
+    /// length.
+    /// 
+    /// This follows the sponge convention of this library rather than circom's: the rate is
+    /// `state[0..5]` and the capacity is `state[5]`. The input may have any length, and since
+    /// a trailing partial block is padded with zeroes (see `Sponge`), the length is encoded in
+    /// the capacity, as in `x5_254_3::hash_roots`, so that e.g. `[a, b, c, d]` and
+    /// `[a, b, c, d, 0]` have different digests. The state is always permuted at least once,
+    /// also for an empty input.
+    /// 
+    /// So a 5-element payload `[a, b, c, d, e]` is hashed to the first element of the
+    /// permutation of `[a, b, c, d, e, 5]`, whereas circom's `Poseidon(5)` permutes
+    /// `[0, a, b, c, d, e]`. Use `compat::hash_circom_compatible` for circom's digests.
+    pub fn hash(input: &[Fr]) -> [Fr; 1] {
+        let mut start_state = [Fr::zero(); 6];
+        start_state[5] = Fr::from(input.len() as u64);
+        let mut sponge = Sponge::<Fr, PoseidonPermutation<ConfigX5_254_6>, 5, 6>::new(start_state);
+        if input.is_empty() {
+            // Nothing is absorbed, so permute the length tag instead of reading the zero rate.
+            sponge.flush();
+        }
+        [sponge.hash(input, 1)[0]]
     }
 }
 
@@ -43,7 +479,16 @@ pub mod x5_254_3 {
 mod tests {
     use super::*;
     use ark_bn254::Fr;
-    use ark_ff::{Zero, One};
+    use ark_ff::{BigInteger, Field, Zero, PrimeField};
+    use crate::sponge::Permutation;
+    use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+    use crate::configurations::poseidon_config::PoseidonConfig;
+
+    #[test]
+    fn test_field_vec_from_u64() {
+        assert_eq!(field_vec_from_u64(&[1, 2, u64::MAX]), vec![Fr::from(1), Fr::from(2), Fr::from(u64::MAX)]);
+        assert!(field_vec_from_u64(&[]).is_empty());
+    }
 
     #[test]
     fn test_hash() {
@@ -58,4 +503,359 @@ mod tests {
         assert_ne!(result_1, result_2);
         assert_eq!(result_1, result_3);
     }
+
+    #[test]
+    fn test_hash_any() {
+        let array = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let vec = array.to_vec();
+        let expected = x5_254_3::hash(&array);
+        assert_eq!(x5_254_3::hash_any(array), expected);
+        assert_eq!(x5_254_3::hash_any(&array[..]), expected);
+        assert_eq!(x5_254_3::hash_any(vec), expected);
+    }
+
+    #[test]
+    fn test_hash_to_array() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let output = x5_254_3::hash_to_array::<3>(&input);
+        let mut sponge = crate::sponge::Sponge::<Fr, x5_254_3::PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_slice(&input);
+        assert_eq!(output.to_vec(), sponge.squeeze_n(3));
+        assert_eq!(x5_254_3::hash_to_array::<1>(&input), x5_254_3::hash(&input));
+        assert_eq!(x5_254_3::hash_to_array::<0>(&input), []);
+    }
+
+    #[test]
+    fn test_hash_rate() {
+        let input = field_vec_from_u64(&[1, 2, 3, 4, 5]);
+        assert_eq!(x5_254_3::hash_rate::<1>(&input), x5_254_3::hash(&input)[0]);
+
+        let mut sponge = crate::sponge::Sponge::<Fr, x5_254_3::PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let rate_2 = x5_254_3::hash_rate::<2>(&input);
+        assert_eq!(rate_2, sponge.hash(&input, 1)[0]);
+        assert_eq!(rate_2, x5_254_3::hash_rate::<2>(&input));
+        assert_ne!(rate_2, x5_254_3::hash_rate::<1>(&input));
+        assert_ne!(rate_2, x5_254_3::hash_rate::<2>(&input[..4]));
+
+        // Trailing zeroes within the last block are absorbed like the zero padding.
+        let a = Fr::from(7);
+        assert_eq!(x5_254_3::hash_rate::<2>(&[a]), x5_254_3::hash_rate::<2>(&[a, Fr::zero()]));
+        assert_ne!(x5_254_3::hash_rate::<1>(&[a]), x5_254_3::hash_rate::<1>(&[a, Fr::zero()]));
+    }
+
+    #[test]
+    fn test_compress_two() {
+        for (a, b) in [(0u64, 0u64), (1, 2), (2, 1), (u64::MAX, 7)] {
+            let (a, b) = (Fr::from(a), Fr::from(b));
+            assert_eq!(x5_254_3::compress_two(a, b), x5_254_3::hash_rate::<2>(&[a, b]));
+        }
+        assert_ne!(x5_254_3::compress_two(Fr::from(1), Fr::from(2)), x5_254_3::compress_two(Fr::from(2), Fr::from(1)));
+        // The inputs are in front of the capacity, unlike in circom's `Poseidon(2)`.
+        assert_ne!(x5_254_3::compress_two(Fr::from(1), Fr::from(2)), crate::compat::hash_two(Fr::from(1), Fr::from(2)));
+    }
+
+    #[test]
+    fn test_hash_fixed_noalloc() {
+        assert_eq!(x5_254_3::hash_fixed_noalloc(&[]), x5_254_3::hash(&[])[0]);
+        assert_eq!(x5_254_3::hash_fixed_noalloc(&[Fr::from(1)]), x5_254_3::hash(&[Fr::from(1)])[0]);
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4)];
+        assert_eq!(x5_254_3::hash_fixed_noalloc(&input), x5_254_3::hash(&input)[0]);
+    }
+
+    #[test]
+    fn test_hash_known_answer() {
+        // With rate 1, every element is absorbed into state[0] followed by a permutation, and
+        // the digest is read from state[0] without a further permutation.
+        let mut state = [Fr::from(1), Fr::zero(), Fr::zero()];
+        x5_254_3::PermX5_254_3::apply(&mut state);
+        assert_eq!(x5_254_3::hash(&[Fr::from(1)]), [state[0]]);
+
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let expected: Fr = ark_ff::MontFp!("14589809886067486815134022374755347411275450448690107297660816198911059968744");
+        assert_eq!(x5_254_3::hash(&input), [expected]);
+    }
+
+    #[test]
+    fn test_hash_fixed_output_bytes() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let bytes = x5_254_3::hash_fixed_output_bytes(&input);
+        assert!(bytes[0] < 0x40, "The two most significant bits should be zero");
+        let reconstructed = Fr::from_be_bytes_mod_order(&bytes);
+        assert_eq!(reconstructed, x5_254_3::hash(&input)[0]);
+    }
+
+    #[test]
+    fn test_digest_to_bytes() {
+        let digest = x5_254_3::hash(&[Fr::from(1), Fr::from(2), Fr::from(3)])[0];
+        let little = x5_254_3::digest_to_bytes(&digest, Endianness::Little);
+        let big = x5_254_3::digest_to_bytes(&digest, Endianness::Big);
+        assert_eq!(little.len(), 32);
+        assert_eq!(little.iter().rev().copied().collect::<Vec<u8>>(), big);
+        assert_eq!(Fr::from_le_bytes_mod_order(&little), digest);
+        assert_eq!(Fr::from_be_bytes_mod_order(&big), digest);
+
+        let one_little = x5_254_3::digest_to_bytes(&Fr::from(1), Endianness::Little);
+        assert_eq!(one_little[0], 1);
+        let one_big = x5_254_3::digest_to_bytes(&Fr::from(1), Endianness::Big);
+        assert_eq!(one_big[31], 1);
+        // The big-endian bytes are the hex string of the parameter files.
+        let constant = ConfigX5_254_3::round_constants()[0];
+        assert_eq!(hex::encode(x5_254_3::digest_to_bytes(&constant, Endianness::Big)), "0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e");
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let digest = x5_254_3::hash(&input)[0];
+        assert!(x5_254_3::verify_hash(&input, digest));
+        assert!(!x5_254_3::verify_hash(&input, digest + Fr::from(1)));
+        assert!(!x5_254_3::verify_hash(&input[..2], digest));
+    }
+
+    #[test]
+    fn test_try_hash() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        assert_eq!(x5_254_3::try_hash(&input), Ok(x5_254_3::hash(&input)[0]));
+        let non_canonical = Fr::new_unchecked(Fr::MODULUS);
+        let input = vec![Fr::from(1), non_canonical, Fr::from(3)];
+        assert_eq!(x5_254_3::try_hash(&input), Err(HashError::NonCanonicalInput(1)));
+    }
+
+    #[test]
+    fn test_hash_normalized() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        assert_eq!(x5_254_3::hash_normalized(&input), x5_254_3::hash(&input)[0]);
+
+        // The representation of 1, plus the modulus, is a non-canonical representation of 1.
+        let mut limbs = Fr::from(1).0;
+        assert!(!limbs.add_with_carry(&Fr::MODULUS));
+        let non_canonical = Fr::new_unchecked(limbs);
+        assert_ne!(non_canonical, Fr::from(1));
+        let input = vec![non_canonical, Fr::from(2), Fr::from(3)];
+        assert!(x5_254_3::try_hash(&input).is_err());
+        assert_eq!(x5_254_3::hash_normalized(&input), x5_254_3::hash(&field_vec_from_u64(&[1, 2, 3]))[0]);
+    }
+
+    #[test]
+    fn test_hash_roots() {
+        let roots: Vec<Fr> = (0..100u64).map(|i| x5_254_3::hash(&[Fr::from(i)])[0]).collect();
+        let aggregate = x5_254_3::hash_roots(&roots);
+        assert_eq!(aggregate, x5_254_3::hash_roots(&roots));
+
+        let mut reordered = roots.clone();
+        reordered.swap(10, 90);
+        assert_ne!(x5_254_3::hash_roots(&reordered), aggregate);
+        assert_ne!(x5_254_3::hash_roots(&roots[..99]), aggregate);
+        assert_ne!(x5_254_3::hash(&roots)[0], aggregate);
+
+        // The length is not absorbed as an ordinary rate element.
+        let mut length_prefixed = vec![Fr::from(roots.len() as u64)];
+        length_prefixed.extend_from_slice(&roots);
+        assert_ne!(x5_254_3::hash(&length_prefixed)[0], aggregate);
+    }
+
+    #[test]
+    fn test_hash_with_separator_byte() {
+        // poseidon([1, 2]) from the circomlib test vectors, which Semaphore uses for its
+        // commitments.
+        let expected: Fr = ark_ff::MontFp!("7853200120776062878684798364095072458815029376092732009249414926327459813530");
+        assert_eq!(x5_254_3::hash_with_separator_byte(0, &[Fr::from(1), Fr::from(2)]), expected);
+        assert_eq!(x5_254_3::semaphore_identity_commitment(Fr::from(1), Fr::from(2)), expected);
+        assert_ne!(x5_254_3::hash_with_separator_byte(1, &[Fr::from(1), Fr::from(2)]), expected);
+    }
+
+    #[test]
+    fn test_semaphore_identity_commitment() {
+        // The identity of the private key b"privateKey" in the TypeScript implementation of
+        // Semaphore v4, from the test vectors at https://github.com/brech1/sem-test-values.
+        let public_key_x: Fr = ark_ff::MontFp!("20191161190634177714856258432742391014210684311546132016070244128804840948064");
+        let public_key_y: Fr = ark_ff::MontFp!("15209227963454794938053687888234270810990820964270375245744800564428536818120");
+        let commitment: Fr = ark_ff::MontFp!("11372478937056182347300323057848769551333725898578571354328589544822167334484");
+        assert_eq!(x5_254_3::semaphore_identity_commitment(public_key_x, public_key_y), commitment);
+    }
+
+    #[test]
+    fn test_hash_with_predicate() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        assert_eq!(x5_254_3::hash_with_predicate(&input, |_| true), x5_254_3::hash(&input)[0]);
+
+        // About one in 12 elements is below 2^250, since the modulus is about 2^253.6.
+        let threshold = Fr::from(2u64).pow([250]).into_bigint();
+        let below = |x: &Fr| x.into_bigint() < threshold;
+        for n in 0..5u64 {
+            let input = vec![Fr::from(n)];
+            let output = x5_254_3::hash_with_predicate(&input, below);
+            assert!(below(&output));
+            assert_eq!(output, x5_254_3::hash_with_predicate(&input, below));
+        }
+    }
+
+    #[test]
+    fn test_keyed_hash() {
+        let message = field_vec_from_u64(&[1, 2, 3]);
+        let mac_1 = x5_254_3::keyed_hash(Fr::from(1), &message);
+        let mac_2 = x5_254_3::keyed_hash(Fr::from(2), &message);
+        assert_ne!(mac_1, mac_2);
+        assert_eq!(mac_1, x5_254_3::keyed_hash(Fr::from(1), &message));
+        assert_ne!(mac_1, x5_254_3::keyed_hash(Fr::from(1), &message[..2]));
+        assert_ne!(x5_254_3::keyed_hash(Fr::from(1), &[]), x5_254_3::keyed_hash(Fr::from(2), &[]));
+        // Even the zero key differs from the unkeyed hash.
+        assert_ne!(x5_254_3::keyed_hash(Fr::zero(), &message), x5_254_3::hash(&message)[0]);
+    }
+
+    #[test]
+    fn test_hash_multi_domain() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let domains = field_vec_from_u64(&[1, 2, 3]);
+        let outputs = x5_254_3::hash_multi_domain(&input, &domains);
+        assert_eq!(outputs, x5_254_3::hash_multi_domain(&input, &domains));
+        assert_eq!(outputs.len(), 3);
+        assert!(outputs[0] != outputs[1] && outputs[1] != outputs[2] && outputs[0] != outputs[2]);
+        for (output, domain) in outputs.iter().zip(&domains) {
+            let mut extended = input.clone();
+            extended.push(*domain);
+            assert_eq!(*output, x5_254_3::hash(&extended)[0]);
+        }
+        assert_eq!(x5_254_3::hash_multi_domain(&input, &[]), vec![]);
+    }
+
+    #[test]
+    fn test_hash_personalized() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        let merkle = x5_254_3::hash_personalized(b"merkle", &input);
+        assert_eq!(merkle, x5_254_3::hash_personalized(b"merkle", &input));
+        assert_ne!(merkle, x5_254_3::hash_personalized(b"nullifier", &input));
+        assert_ne!(merkle, x5_254_3::hash(&input)[0]);
+        // Trailing zero bytes are not lost in the packing.
+        assert_ne!(x5_254_3::hash_personalized(b"a", &input), x5_254_3::hash_personalized(b"a\0", &input));
+        assert_ne!(x5_254_3::hash_personalized(b"", &[]), x5_254_3::hash_personalized(b"a", &[]));
+    }
+
+    #[test]
+    fn test_length_extension() {
+        let message = field_vec_from_u64(&[1, 2, 3]);
+        let extension = vec![Fr::from(4), Fr::from(5)];
+        let extended: Vec<Fr> = message.iter().chain(extension.iter()).copied().collect();
+        let expected = x5_254_3::hash(&extended)[0];
+
+        // The digest is the rate of the final state, so it is a part of the state from which
+        // the extended hash continues. Without the capacity, the extension cannot be computed.
+        let digest = x5_254_3::hash(&message)[0];
+        let mut guessed = crate::sponge::Sponge::<Fr, x5_254_3::PermX5_254_3, 1, 3>::new([digest, Fr::zero(), Fr::zero()]);
+        assert_ne!(guessed.hash(&extension, 1)[0], expected);
+
+        // With the full state, which is never output by the sponge, the extension is possible.
+        let mut state = [Fr::zero(); 3];
+        assert_eq!(x5_254_3::hash_in_place(&mut state, &message), digest);
+        assert_eq!(state[0], digest);
+        let mut continued = crate::sponge::Sponge::<Fr, x5_254_3::PermX5_254_3, 1, 3>::new(state);
+        assert_eq!(continued.hash(&extension, 1)[0], expected);
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+        use crate::configurations::optimized_constants::OptimizedConstants;
+        use crate::dyn_sponge::DynSponge;
+        use crate::safe_sponge::SafeSponge;
+        use crate::sponge::Sponge;
+        use crate::sponge_pool::SpongePool;
+        use crate::transcript::PoseidonTranscript;
+        use x5_254_3::PermX5_254_3;
+
+        assert_send_sync::<ConfigX5_254_3>();
+        assert_send_sync::<PermX5_254_3>();
+        assert_send_sync::<Sponge<Fr, PermX5_254_3, 2, 3>>();
+        assert_send_sync::<Sponge<Fr, PermX5_254_3, 2, 3, &mut [Fr; 3]>>();
+        assert_send_sync::<SafeSponge<Fr, PermX5_254_3, 2, 3>>();
+        assert_send_sync::<DynSponge<Fr>>();
+        assert_send_sync::<SpongePool<Fr, PermX5_254_3, 2, 3>>();
+        assert_send_sync::<PoseidonTranscript<Fr, PermX5_254_3, 2, 3>>();
+        assert_send_sync::<OptimizedConstants<Fr, 3>>();
+        // The parameters of a config are shared between threads through `Lazy`.
+        assert_send_sync::<&'static [Fr]>();
+    }
+
+    #[test]
+    fn test_hash_and_continue() {
+        let first = field_vec_from_u64(&[1, 2, 3]);
+        let second = field_vec_from_u64(&[4, 5]);
+        let (digest, mut sponge) = x5_254_3::hash_and_continue(&first);
+        assert_eq!([digest], x5_254_3::hash(&first));
+        sponge.absorb_slice(&second);
+        let continued = sponge.squeeze()[0];
+
+        // The same as a single sponge absorbing both inputs, with a squeeze in between.
+        let mut combined = crate::sponge::Sponge::<Fr, x5_254_3::PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        combined.absorb_slice(&first);
+        assert_eq!(combined.squeeze()[0], digest);
+        combined.absorb_slice(&second);
+        assert_eq!(combined.squeeze()[0], continued);
+        assert_ne!(continued, x5_254_3::hash(&second)[0]);
+    }
+
+    #[test]
+    fn test_hash_iter() {
+        let input: Vec<Fr> = (1..1000).map(Fr::from).collect();
+        assert_eq!(x5_254_3::hash_iter((1..1000).map(Fr::from)), x5_254_3::hash(&input)[0]);
+        assert_eq!(x5_254_3::hash_iter(std::iter::empty()), x5_254_3::hash(&[])[0]);
+    }
+
+    #[test]
+    fn test_derive_nonce() {
+        let secret = Fr::from(0x5ec2e7u64);
+        let message = field_vec_from_u64(&[1, 2, 3]);
+        let nonce = x5_254_3::derive_nonce(secret, &message);
+        assert_eq!(nonce, x5_254_3::derive_nonce(secret, &message));
+        assert_ne!(nonce, x5_254_3::derive_nonce(secret, &field_vec_from_u64(&[1, 2, 4])));
+        assert_ne!(nonce, x5_254_3::derive_nonce(secret, &message[..2]));
+        assert_ne!(nonce, x5_254_3::derive_nonce(secret + Fr::from(1), &message));
+        assert_eq!(nonce, x5_254_3::keyed_hash(secret, &field_vec_from_u64(&[0, 3, 1, 2, 3])));
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let input = field_vec_from_u64(&[1, 2, 3]);
+        assert_eq!(poseidon_hash::<Fr, ConfigX5_254_3, 1, 3>(&input), x5_254_3::hash(&input)[0]);
+        assert_eq!(poseidon_hash::<Fr, ConfigX5_254_3, 2, 3>(&input), x5_254_3::hash_rate::<2>(&input));
+    }
+
+    #[test]
+    fn test_hash_in_place() {
+        let mut state = [Fr::from(123); 3];
+        for n in 0..5 {
+            let input: Vec<Fr> = (0..n).map(Fr::from).collect();
+            assert_eq!(x5_254_3::hash_in_place(&mut state, &input), x5_254_3::hash(&input)[0]);
+        }
+    }
+
+    #[test]
+    fn test_x5_254_6_hash() {
+        use crate::configurations::config_x5_254_6::ConfigX5_254_6;
+        let input = field_vec_from_u64(&[1, 2, 3, 4, 5]);
+        let digest = x5_254_6::hash(&input)[0];
+        assert_eq!(digest, x5_254_6::hash(&input)[0]);
+
+        let expected: Fr = ark_ff::MontFp!("11530537984290553183828999466703237417573688432078369722267054021423207876751");
+        assert_eq!(digest, expected);
+
+        // A 5-element payload needs a single permutation, with the digest in the first element.
+        let mut state = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4), Fr::from(5), Fr::from(5)];
+        crate::permutation::perm::<Fr, 6, ConfigX5_254_6>(&mut state);
+        assert_eq!(digest, state[0]);
+
+        assert_ne!(digest, x5_254_6::hash(&field_vec_from_u64(&[1, 2, 3, 4, 6]))[0]);
+        assert_ne!(digest, x5_254_6::hash(&field_vec_from_u64(&[5, 4, 3, 2, 1]))[0]);
+        assert_ne!(digest, x5_254_3::hash(&input)[0]);
+
+        // Trailing zeroes change the encoded length, and the empty input is permuted.
+        let four = field_vec_from_u64(&[1, 2, 3, 4]);
+        assert_ne!(x5_254_6::hash(&four), x5_254_6::hash(&field_vec_from_u64(&[1, 2, 3, 4, 0])));
+        let mut empty = [Fr::zero(); 6];
+        crate::permutation::perm::<Fr, 6, ConfigX5_254_6>(&mut empty);
+        assert_eq!(x5_254_6::hash(&[]), [empty[0]]);
+        assert_ne!(x5_254_6::hash(&[]), [Fr::zero()]);
+    }
 }
\ No newline at end of file
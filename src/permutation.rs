@@ -1,14 +1,31 @@
 //! This module implements a general Poseidon permutation function. Given a concrete
 //! Poseidon configuration, the `perm` function applies the Poseidon permutation.
+//!
+//! Regarding timing side channels: the round constants, the MDS multiplications and the
+//! `x^ALPHA` S-box (a fixed addition chain or a public exponent) perform the same sequence
+//! of field operations for every state, in the full as well as the partial rounds. The
+//! inverse S-box is variable time by default, since both `Field::inverse` and the batched
+//! inversion of the full rounds depend on the input, unless the config sets
+//! `CONSTANT_TIME_INVERSION`. None of this makes the underlying arkworks field arithmetic
+//! constant time, see the crate documentation.
 
-use ark_ff::Field;
+use std::marker::PhantomData;
 
+use ark_ff::{Field, PrimeField};
 
-use crate::configurations::poseidon_config::PoseidonConfig;
+
+use crate::configurations::poseidon_config::{PoseidonConfig, sbox_pow5, batch_inverse};
+use crate::configurations::optimized_constants::OptimizedConstants;
+use crate::sponge::Permutation;
 
 /// A helper function to perform matrix-vector multiplication.
 /// This could probably be optimized.
-fn matrix_vector_mul<F: Field, const T: usize>(matrix: &[[F; T]; T], vector: &[F; T]) -> [F; T] {
+/// 
+/// The matrix is expected in row-major layout, i.e. `matrix[i]` is the `i`th row, and the
+/// result is `result[i] = sum_j matrix[i][j] * vector[j]`. All MDS matrices in this library
+/// use this layout, see `transpose_matrix` for importing matrices from references which
+/// store them transposed.
+pub fn matrix_vector_mul<F: Field, const T: usize>(matrix: &[[F; T]; T], vector: &[F; T]) -> [F; T] {
     let mut result = [F::zero(); T];
     for i in 0..T {
         for j in 0..T {
@@ -18,44 +35,287 @@ fn matrix_vector_mul<F: Field, const T: usize>(matrix: &[[F; T]; T], vector: &[F
     result
 }
 
+/// Multiplies a matrix given in column-major layout (e.g. by `mds_matrix_transposed`) with a
+/// vector. The result is the same as `matrix_vector_mul` with the row-major matrix, but the
+/// matrix is traversed column by column, adding `vector[j]` times the `j`th column to the
+/// result, which may access memory more favourably for wide states.
+pub fn matrix_vector_mul_transposed<F: Field, const T: usize>(columns: &[[F; T]; T], vector: &[F; T]) -> [F; T] {
+    let mut result = [F::zero(); T];
+    for (column, x) in columns.iter().zip(vector.iter()) {
+        for (word, element) in result.iter_mut().zip(column.iter()) {
+            *word += *element * x;
+        }
+    }
+    result
+}
+
+/// Transposes a `T x T` matrix, i.e. converts between row-major and column-major layouts.
+/// 
+/// Some references (e.g. Neptune) multiply the state as a row vector from the left with the
+/// MDS matrix, so their matrices are the transposes of the ones expected by this library.
+/// Such matrices must be transposed before being used in a `PoseidonConfig`.
+pub fn transpose_matrix<F: Field, const T: usize>(m: &[[F; T]; T]) -> [[F; T]; T] {
+    let mut result = [[F::zero(); T]; T];
+    for (i, row) in m.iter().enumerate() {
+        for (j, element) in row.iter().enumerate() {
+            result[j][i] = *element;
+        }
+    }
+    result
+}
+
+
+/// Logs the state after a round of the permutation. This is only done when the `trace`
+/// feature is enabled, and is otherwise a no-op.
+#[cfg(feature = "trace")]
+fn trace_round<F: Field, const T: usize>(round: usize, full: bool, state: &[F; T]) {
+    tracing::trace!(round, full, state = ?state, "Poseidon round");
+}
+
+#[cfg(not(feature = "trace"))]
+#[inline(always)]
+fn trace_round<F: Field, const T: usize>(_round: usize, _full: bool, _state: &[F; T]) {}
+
 
 /// Performs the Poseidon permutation on the input words.
+/// 
+/// With the `trace` feature enabled, the state after each round is logged as a `tracing`
+/// event at the trace level.
+/// 
+/// Panics if the config does not have exactly `T * (R_F + R_P)` round constants, in debug
+/// and release builds alike, since a mismatch means that the config is not the intended one.
 pub fn perm<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T]) {
-    let R_f = P::R_F/ 2;
+    perm_with_observer::<F, T, P>(input_words, trace_round);
+}
+
+/// The Poseidon permutation of the configuration `C`, for use with the sponge constructions,
+/// e.g. `Sponge<F, PoseidonPermutation<C>, RATE, T>`.
+pub struct PoseidonPermutation<C>(PhantomData<fn() -> C>);
+
+impl<F: Field, const T: usize, C: PoseidonConfig<F, T>> Permutation<F, T> for PoseidonPermutation<C> {
+    fn apply(state: &mut [F; T]) {
+        perm::<F, T, C>(state);
+    }
+}
+
+/// Returns the Poseidon permutation of the config `P` as a closure, so that it can be passed
+/// as a value, e.g. to generic combinators expecting an `Fn(&mut [F; T])`.
+pub fn permutation_fn<F: Field, const T: usize, P: PoseidonConfig<F,T>>() -> impl Fn(&mut [F; T]) {
+    perm::<F, T, P>
+}
+
+/// Performs the Poseidon permutation on the given initial state, and returns the state after
+/// every round, e.g. for generating the witness of a circuit computing the permutation.
+/// 
+/// The trace has `R_F + R_P` entries, where the last one is the result of `perm`.
+pub fn perm_trace<F: Field, const T: usize, P: PoseidonConfig<F,T>>(mut state: [F; T]) -> Vec<[F; T]> {
+    let mut trace = Vec::with_capacity(P::R_F + P::R_P);
+    perm_with_observer::<F, T, P>(&mut state, |_, _, round_state| trace.push(*round_state));
+    trace
+}
+
+/// Dumps the state of the permutation round by round in the format of circom's witness
+/// files (as exported by `snarkjs wtns export json`), i.e. as a JSON array of decimal
+/// strings, for diffing against the trace of a circom execution to localize divergences.
+/// 
+/// The array holds the initial state followed by the states of `perm_trace`, so it has
+/// `T * (R_F + R_P + 1)` elements, with one element per line.
+pub fn perm_trace_circom<F: PrimeField, const T: usize, P: PoseidonConfig<F,T>>(state: [F; T]) -> String {
+    let elements: Vec<String> = std::iter::once(state)
+        .chain(perm_trace::<F, T, P>(state))
+        .flatten()
+        .map(|element| format!(" \"{}\"", element.into_bigint()))
+        .collect();
+    format!("[\n{}\n]", elements.join(",\n"))
+}
+
+/// Performs the Poseidon permutation, calling `observe` with the round number, whether the
+/// round is full, and the state after each round.
+#[inline(always)]
+fn perm_with_observer<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T], mut observe: impl FnMut(usize, bool, &[F; T])) {
+    let r_f = P::R_F/ 2;
     let mut round_constants_counter = 0;
 
-    for _ in 0..R_f {
-        for i in 0..T {
-            input_words[i] = input_words[i] + P::round_constants()[round_constants_counter];
-            round_constants_counter += 1;
-        }
-        for i in 0..T {
-            input_words[i] = P::sbox(&input_words[i]);
-        }
-        *input_words = matrix_vector_mul(&P::mds_matrix(), &input_words);
+    if P::HAS_INITIAL_MDS {
+        *input_words = matrix_vector_mul(P::mds_matrix(), input_words);
+    }
+
+    for round in 0..r_f {
+        apply_round::<F, T, P>(input_words, &mut round_constants_counter, true);
+        observe(round, true, input_words);
+    }
+
+    for round in 0..P::R_P {
+        apply_round::<F, T, P>(input_words, &mut round_constants_counter, false);
+        observe(r_f + round, false, input_words);
+    }
+
+    for round in 0..r_f {
+        apply_round::<F, T, P>(input_words, &mut round_constants_counter, true);
+        observe(r_f + P::R_P + round, true, input_words);
+    }
+
+    assert_eq!(round_constants_counter, P::round_constants().len(), "The number of round constants should be T * (R_F + R_P)");
+}
+
+/// Applies `rounds` full rounds of the config `P` to the state, using the round constants
+/// starting at the index `round_constants_offset`, and returns the offset of the constants
+/// following the last round. This lets the phases of the permutation be tested separately
+/// and composed into custom schedules: `perm` is `R_F / 2` full rounds, `R_P` partial rounds
+/// and `R_F / 2` full rounds, starting at offset 0 (after the initial linear layer, if the
+/// config has one, which is not applied here).
+/// 
+/// Panics if the round constants do not cover the requested rounds.
+pub fn apply_full_rounds<F: Field, const T: usize, P: PoseidonConfig<F,T>>(state: &mut [F; T], rounds: usize, round_constants_offset: usize) -> usize {
+    let mut round_constants_counter = round_constants_offset;
+    for _ in 0..rounds {
+        apply_round::<F, T, P>(state, &mut round_constants_counter, true);
+    }
+    round_constants_counter
+}
+
+/// Applies `rounds` partial rounds of the config `P` to the state, using the round constants
+/// starting at the index `round_constants_offset`, and returns the offset of the constants
+/// following the last round. See `apply_full_rounds`.
+pub fn apply_partial_rounds<F: Field, const T: usize, P: PoseidonConfig<F,T>>(state: &mut [F; T], rounds: usize, round_constants_offset: usize) -> usize {
+    let mut round_constants_counter = round_constants_offset;
+    for _ in 0..rounds {
+        apply_round::<F, T, P>(state, &mut round_constants_counter, false);
     }
+    round_constants_counter
+}
+
+/// Adds the round constants of the next round to the state, starting at the given position
+/// in the round constants. Only the first `ROUND_CONSTANT_WIDTH` elements receive a constant,
+/// but the position always advances by `T`.
+#[inline(always)]
+fn add_round_constants<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T], round_constants_counter: &mut usize) {
+    let constants = &P::round_constants()[*round_constants_counter..*round_constants_counter + T];
+    for (word, constant) in input_words.iter_mut().zip(constants).take(P::ROUND_CONSTANT_WIDTH) {
+        *word += constant;
+    }
+    *round_constants_counter += T;
+}
 
-    for _ in 0..P::R_P {
-        for i in 0..T {
-            input_words[i] = input_words[i] + P::round_constants()[round_constants_counter];
-            round_constants_counter += 1;
+/// Applies a single full or partial round to the state: the round constants and the S-box
+/// layer, in the order given by `CONSTANTS_BEFORE_SBOX`, followed by the MDS matrix.
+#[inline(always)]
+fn apply_round<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T], round_constants_counter: &mut usize, full: bool) {
+    if P::CONSTANTS_BEFORE_SBOX {
+        add_round_constants::<F, T, P>(input_words, round_constants_counter);
+    }
+    if full {
+        full_sbox_layer::<F, T, P>(input_words);
+    } else {
+        input_words[P::PARTIAL_SBOX_INDEX] = P::sbox(&input_words[P::PARTIAL_SBOX_INDEX]);
+    }
+    if !P::CONSTANTS_BEFORE_SBOX {
+        add_round_constants::<F, T, P>(input_words, round_constants_counter);
+    }
+    *input_words = matrix_vector_mul(P::mds_matrix(), input_words);
+}
+
+/// Applies the S-box to every element of the state, as in a full round. For the inverse
+/// S-box, the inverses are computed with a single batched inversion, unless constant-time
+/// inversion is requested, which bypasses `P::sbox` (see its documentation).
+#[inline(always)]
+fn full_sbox_layer<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T]) {
+    if P::INVERSE_SBOX && !P::CONSTANT_TIME_INVERSION {
+        batch_inverse(input_words);
+    } else {
+        for word in input_words.iter_mut() {
+            *word = P::sbox(word);
         }
-        input_words[0] = P::sbox(&input_words[0]);
-        *input_words = matrix_vector_mul(&P::mds_matrix(), &input_words);
     }
+}
+
+/// The representation of the linear layers used by `perm_auto`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinearLayerPath {
+    /// The dense MDS matrix in every round, as in `perm`.
+    Dense,
+    /// The dense MDS matrix in the full rounds and sparse matrices in the partial rounds, as
+    /// in `perm_optimized`.
+    Sparse,
+}
 
-    for _ in 0..R_f {
-        for i in 0..T {
-            input_words[i] = input_words[i] + P::round_constants()[round_constants_counter];
-            round_constants_counter += 1;
+/// Performs the Poseidon permutation on the input words, choosing the representation of the
+/// linear layers automatically, and returns the representation which was used.
+/// 
+/// The sparse representation needs `2T - 1` instead of `T^2` multiplications per partial
+/// round, which is fewer for any `T >= 2`. It is therefore used whenever the config provides
+/// optimized constants and has partial rounds. Otherwise, this falls back to `perm`. Both
+/// representations compute the same permutation.
+pub fn perm_auto<F: Field, const T: usize, P: PoseidonConfig<F,T>>(input_words: &mut [F; T]) -> LinearLayerPath {
+    match P::optimized_constants() {
+        Some(constants) if T >= 2 && P::R_P > 0 => {
+            perm_optimized(input_words, constants);
+            LinearLayerPath::Sparse
         }
-        for i in 0..T {
-            input_words[i] = P::sbox(&input_words[i]);
+        _ => {
+            perm::<F, T, P>(input_words);
+            LinearLayerPath::Dense
         }
-        *input_words = matrix_vector_mul(&P::mds_matrix(), &input_words);
     }
 }
 
+/// Computes the S-box `x^alpha` for a runtime exponent.
+pub(crate) fn sbox_runtime<F: Field>(x: &F, alpha: u64) -> F {
+    if alpha == 5 {
+        sbox_pow5(x)
+    } else {
+        x.pow([alpha])
+    }
+}
+
+/// Performs the optimized Poseidon permutation on the input words, using compressed round
+/// constants and sparse matrices for the partial rounds (see `OptimizedConstants`).
+/// The partial-round S-box is always applied to the first element, as in Neptune.
+/// 
+/// Given constants derived from a configuration, the result is the same as for `perm`, but
+/// each partial round only needs `2T - 1` multiplications for the linear layer, instead of
+/// `T^2`.
+pub fn perm_optimized<F: Field, const T: usize>(input_words: &mut [F; T], constants: &OptimizedConstants<F, T>) {
+    let half_full_rounds = constants.r_f / 2;
+    let num_rounds = constants.r_f + constants.r_p;
+    let round_constants = &constants.compressed_round_constants;
+
+    for (word, constant) in input_words.iter_mut().zip(round_constants.iter()) {
+        *word += constant;
+    }
+    let mut round_constants_counter = T;
+
+    for round in 0..num_rounds {
+        let is_full_round = round < half_full_rounds || round >= half_full_rounds + constants.r_p;
+        if is_full_round {
+            for word in input_words.iter_mut() {
+                *word = sbox_runtime(word, constants.alpha);
+            }
+        } else {
+            input_words[0] = sbox_runtime(&input_words[0], constants.alpha);
+        }
+
+        if round + 1 < num_rounds {
+            if is_full_round {
+                for word in input_words.iter_mut() {
+                    *word += round_constants[round_constants_counter];
+                    round_constants_counter += 1;
+                }
+            } else {
+                input_words[0] += round_constants[round_constants_counter];
+                round_constants_counter += 1;
+            }
+        }
+
+        *input_words = if round + 1 == half_full_rounds {
+            matrix_vector_mul(&constants.pre_sparse_matrix, input_words)
+        } else if is_full_round {
+            matrix_vector_mul(&constants.mds_matrix, input_words)
+        } else {
+            constants.sparse_matrices[round - half_full_rounds].apply(input_words)
+        };
+    }
+}
 
 
 #[cfg(test)]
@@ -68,13 +328,17 @@ mod tests {
     use ark_ff::{Zero, One};
     use once_cell::sync::Lazy;
     use crate::configurations::config_x5_254_3::ConfigX5_254_3;
-    use crate::configurations::config_creation_helper_functions::parse_constants;
+    use crate::configurations::config_creation_helper_functions::{parse_constants, parse_matrix};
+    use crate::configurations::optimized_constants::import_neptune_constants;
+    use crate::sponge::{Permutation, Sponge};
+    use crate::test_fields::F101;
 
 
     struct IdentityConfig;
     impl PoseidonConfig<Fr, 3> for IdentityConfig {
         const R_F: usize = 2;
         const R_P: usize = 3;
+        const ALPHA: u64 = 1;
 
         fn mds_matrix() -> &'static [[Fr; 3]; 3] {
             static IDENTITY_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
@@ -100,12 +364,12 @@ mod tests {
     #[test]
     fn test_identity() {
         let mut input = [Fr::one(), Fr::zero(), Fr::zero()];
-        let expected = input.clone();
+        let expected = input;
         perm::<Fr, 3, IdentityConfig>(&mut input);
         assert_eq!(input, expected);
 
         let mut input = [Fr::from(32543)*Fr::one(), Fr::from(865324)*Fr::one(), Fr::from(987676534)*Fr::one()];
-        let expected = input.clone();
+        let expected = input;
         perm::<Fr, 3, IdentityConfig>(&mut input);
         assert_eq!(input, expected);
     }
@@ -114,6 +378,7 @@ mod tests {
     impl PoseidonConfig<Fr, 3> for SboxConfig {
         const R_F: usize = 2;
         const R_P: usize = 3;
+        const ALPHA: u64 = 5;
 
         fn mds_matrix() -> &'static [[Fr; 3]; 3] {
             static IDENTITY_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
@@ -131,10 +396,6 @@ mod tests {
             });
             &ZEROES
         }
-
-        fn sbox(x: &Fr) -> Fr {
-            x.pow([5])
-        }
     }
 
     #[test]
@@ -148,10 +409,38 @@ mod tests {
         assert_eq!(input, expected);
     }
 
+    struct LastIndexSboxConfig;
+    impl PoseidonConfig<Fr, 3> for LastIndexSboxConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 3;
+        const ALPHA: u64 = 5;
+        const PARTIAL_SBOX_INDEX: usize = 2;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            SboxConfig::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            SboxConfig::round_constants()
+        }
+    }
+
+    #[test]
+    fn test_partial_sbox_index() {
+        let first = Fr::from(2);
+        let second = Fr::from(3);
+        let third = Fr::from(4);
+        let mut input = [first, second, third];
+        let expected = [first.pow([5*5]), second.pow([5*5]), third.pow([5*5*5*5*5])];
+        perm::<Fr, 3, LastIndexSboxConfig>(&mut input);
+        assert_eq!(input, expected);
+    }
+
     struct RoundConstantConfig;
     impl PoseidonConfig<Fr, 3> for RoundConstantConfig {
         const R_F: usize = 2;
         const R_P: usize = 3;
+        const ALPHA: u64 = 1;
 
         fn mds_matrix() -> &'static [[Fr; 3]; 3] {
             static IDENTITY_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
@@ -186,10 +475,85 @@ mod tests {
         assert_eq!(input, expected);
     }
 
+    struct ZeroPartialRoundsIdentityConfig;
+    impl PoseidonConfig<Fr, 3> for ZeroPartialRoundsIdentityConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 0;
+        const ALPHA: u64 = 1;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            IdentityConfig::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            &IdentityConfig::round_constants()[..3*2]
+        }
+
+        fn sbox(x: &Fr) -> Fr {
+            *x
+        }
+    }
+
+    struct ZeroPartialRoundsConfig;
+    impl PoseidonConfig<Fr, 3> for ZeroPartialRoundsConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 0;
+        const ALPHA: u64 = 1;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            IdentityConfig::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            &RoundConstantConfig::round_constants()[..3*2]
+        }
+
+        fn sbox(x: &Fr) -> Fr {
+            *x
+        }
+    }
+
+    #[test]
+    fn test_zero_partial_rounds() {
+        let mut input = [Fr::from(32543), Fr::from(865324), Fr::from(987676534)];
+        let expected = input;
+        perm::<Fr, 3, ZeroPartialRoundsIdentityConfig>(&mut input);
+        assert_eq!(input, expected);
+
+        // Only the constants of the two full rounds are added.
+        let mut input = [Fr::one(), Fr::one(), Fr::one()];
+        let expected = [Fr::from(4), Fr::from(31), Fr::from(301)];
+        perm::<Fr, 3, ZeroPartialRoundsConfig>(&mut input);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unused_round_constants_are_detected() {
+        // The identity config has constants for 3 partial rounds, which are not used here.
+        struct TooManyConstantsConfig;
+        impl PoseidonConfig<Fr, 3> for TooManyConstantsConfig {
+            const R_F: usize = 2;
+            const R_P: usize = 0;
+            const ALPHA: u64 = 1;
+
+            fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+                IdentityConfig::mds_matrix()
+            }
+
+            fn round_constants() -> &'static [Fr] {
+                IdentityConfig::round_constants()
+            }
+        }
+        let mut input = [Fr::one(), Fr::one(), Fr::one()];
+        perm::<Fr, 3, TooManyConstantsConfig>(&mut input);
+    }
+
     struct MatrixConfig;
     impl PoseidonConfig<Fr, 3> for MatrixConfig {
         const R_F: usize = 2;
         const R_P: usize = 3;
+        const ALPHA: u64 = 1;
 
         fn mds_matrix() -> &'static [[Fr; 3]; 3] {
             static MDS_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
@@ -230,4 +594,543 @@ mod tests {
             assert_eq!(input[i], expected[i], "Mismatch at index {}", i);
         }
     }
+
+    #[cfg(feature = "bls12_381")]
+    #[test]
+    fn test_x5_255_3() {
+        // The poseidonperm_x5_255_3 test vector of the reference implementation.
+        use ark_bls12_381::Fr as BlsFr;
+        use crate::configurations::config_x5_255_3::ConfigX5_255_3;
+        let mut input = [BlsFr::from(0), BlsFr::from(1), BlsFr::from(2)];
+        let expected = parse_constants::<BlsFr>("28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a\n51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4\n3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a");
+        perm::<BlsFr, 3, ConfigX5_255_3>(&mut input);
+        assert_eq!(input.to_vec(), expected);
+    }
+
+    /// The x5_254_3 configuration with its MDS matrix imported from a reference storing it
+    /// in column-major layout. If `TRANSPOSE` is set, the matrix is transposed on import.
+    struct ColumnMajorX5_254_3<const TRANSPOSE: bool>;
+    impl<const TRANSPOSE: bool> PoseidonConfig<Fr, 3> for ColumnMajorX5_254_3<TRANSPOSE> {
+        const R_F: usize = 8;
+        const R_P: usize = 57;
+        const ALPHA: u64 = 5;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            static COLUMN_MAJOR: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
+                parse_matrix([
+                    "109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b 2969f27eed31a480b9c36c764379dbca2cc8fdd1415c3dded62940bcde0bd771 143021ec686a3f330d5f9e654638065ce6cd79e28c5b3753326244ee65a1b1a7",
+                    "16ed41e13bb9c0c66ae119424fddbcbc9314dc9fdbdeea55d6c64543dc4903e0 2e2419f9ec02ec394c9871c832963dc1b89d743c8c7b964029b2311687b1fe23 176cc029695ad02582a70eff08a6fd99d057e12e58e7d7b6b16cdfabc8ee2911",
+                    "2b90bba00fca0589f617e7dcbfe82e0df706ab640ceb247b791a93b74e36736d 101071f0032379b697315876690f053d148d4e109f5fb065c8aacc55a0f89bfa 19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0"
+                ])
+            });
+            static TRANSPOSED: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| transpose_matrix(&COLUMN_MAJOR));
+            if TRANSPOSE { &TRANSPOSED } else { &COLUMN_MAJOR }
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            ConfigX5_254_3::round_constants()
+        }
+    }
+
+    /// The x5_254_3 parameters with the inverse S-box.
+    struct InverseSboxConfig;
+    impl PoseidonConfig<Fr, 3> for InverseSboxConfig {
+        const R_F: usize = 8;
+        const R_P: usize = 57;
+        const ALPHA: u64 = 5;
+        const INVERSE_SBOX: bool = true;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            ConfigX5_254_3::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            ConfigX5_254_3::round_constants()
+        }
+    }
+
+    /// The x5_254_3 parameters with the inverse S-box and constant-time inversion.
+    struct ConstantTimeInverseSboxConfig;
+    impl PoseidonConfig<Fr, 3> for ConstantTimeInverseSboxConfig {
+        const R_F: usize = 8;
+        const R_P: usize = 57;
+        const ALPHA: u64 = 5;
+        const INVERSE_SBOX: bool = true;
+        const CONSTANT_TIME_INVERSION: bool = true;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            ConfigX5_254_3::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            ConfigX5_254_3::round_constants()
+        }
+    }
+
+    #[test]
+    fn test_constant_time_inversion() {
+        for input in [[Fr::from(0), Fr::from(1), Fr::from(2)], [Fr::from(0); 3]] {
+            let mut expected = input;
+            perm::<Fr, 3, InverseSboxConfig>(&mut expected);
+            let mut state = input;
+            perm::<Fr, 3, ConstantTimeInverseSboxConfig>(&mut state);
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn test_inverse_sbox() {
+        let mut input = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        let mut expected = input;
+        for (round, constants) in InverseSboxConfig::round_constants().chunks(3).enumerate() {
+            for (word, constant) in expected.iter_mut().zip(constants) {
+                *word += constant;
+            }
+            if !(4..4 + 57).contains(&round) {
+                for word in expected.iter_mut() {
+                    *word = word.inverse().unwrap_or(Fr::zero());
+                }
+            } else {
+                expected[0] = InverseSboxConfig::sbox(&expected[0]);
+            }
+            expected = matrix_vector_mul(InverseSboxConfig::mds_matrix(), &expected);
+        }
+        perm::<Fr, 3, InverseSboxConfig>(&mut input);
+        assert_eq!(input, expected);
+    }
+
+    /// The x5_254_3 parameters with an initial linear layer.
+    struct InitialMdsConfig;
+    impl PoseidonConfig<Fr, 3> for InitialMdsConfig {
+        const R_F: usize = 8;
+        const R_P: usize = 57;
+        const ALPHA: u64 = 5;
+        const HAS_INITIAL_MDS: bool = true;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            ConfigX5_254_3::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            ConfigX5_254_3::round_constants()
+        }
+    }
+
+    #[test]
+    fn test_initial_mds() {
+        let input = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        let mut expected = matrix_vector_mul(ConfigX5_254_3::mds_matrix(), &input);
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        let mut state = input;
+        perm::<Fr, 3, InitialMdsConfig>(&mut state);
+        assert_eq!(state, expected);
+
+        // The optimized permutation does not support the initial linear layer.
+        assert!(crate::configurations::optimized_constants::derive_optimized_constants::<Fr, 3, InitialMdsConfig>().is_none());
+        let mut state = input;
+        assert_eq!(perm_auto::<Fr, 3, InitialMdsConfig>(&mut state), LinearLayerPath::Dense);
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_matrix_vector_mul_transposed() {
+        use ark_std::{test_rng, UniformRand};
+        let mut rng = test_rng();
+        let vector = [Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        assert_eq!(
+            matrix_vector_mul_transposed(ConfigX5_254_3::mds_matrix_transposed(), &vector),
+            matrix_vector_mul(ConfigX5_254_3::mds_matrix(), &vector)
+        );
+        let wide: [[Fr; 12]; 12] = std::array::from_fn(|_| std::array::from_fn(|_| Fr::rand(&mut rng)));
+        let vector: [Fr; 12] = std::array::from_fn(|_| Fr::rand(&mut rng));
+        assert_eq!(matrix_vector_mul_transposed(&transpose_matrix(&wide), &vector), matrix_vector_mul(&wide, &vector));
+    }
+
+    /// Two full rounds with the identity as linear layer and the S-box `x^2`, adding the round
+    /// constants before or after the S-box.
+    struct SquaringConfig<const BEFORE: bool>;
+    impl<const BEFORE: bool> PoseidonConfig<Fr, 3> for SquaringConfig<BEFORE> {
+        const R_F: usize = 2;
+        const R_P: usize = 0;
+        const ALPHA: u64 = 2;
+        const CONSTANTS_BEFORE_SBOX: bool = BEFORE;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            IdentityConfig::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            static CONSTANTS: Lazy<Vec<Fr>> = Lazy::new(|| (1..=6u64).map(Fr::from).collect());
+            &CONSTANTS
+        }
+    }
+
+    #[test]
+    fn test_constants_before_sbox() {
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        // ((x + c_0)^2 + c_1)^2 for the standard order.
+        let mut state = input;
+        perm::<Fr, 3, SquaringConfig<true>>(&mut state);
+        assert_eq!(state, [Fr::from(64), Fr::from(441), Fr::from(1764)]);
+        // (x^2 + c_0)^2 + c_1 with the S-box first.
+        let mut state = input;
+        perm::<Fr, 3, SquaringConfig<false>>(&mut state);
+        assert_eq!(state, [Fr::from(8), Fr::from(41), Fr::from(150)]);
+        assert!(crate::configurations::optimized_constants::derive_optimized_constants::<Fr, 3, SquaringConfig<false>>().is_none());
+    }
+
+    /// A config adding the round constants only to the rate of a rate-2 sponge, with the
+    /// identity as linear layer and S-box, so that the state accumulates the constants.
+    struct RateConstantsConfig;
+    impl PoseidonConfig<Fr, 3> for RateConstantsConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 3;
+        const ALPHA: u64 = 1;
+        const ROUND_CONSTANT_WIDTH: usize = 2;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            IdentityConfig::mds_matrix()
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            static CONSTANTS: Lazy<Vec<Fr>> = Lazy::new(|| (1..=15u64).map(Fr::from).collect());
+            &CONSTANTS
+        }
+
+        fn sbox(x: &Fr) -> Fr {
+            *x
+        }
+    }
+
+    #[test]
+    fn test_round_constant_width() {
+        let mut state = [Fr::zero(); 3];
+        perm::<Fr, 3, RateConstantsConfig>(&mut state);
+        // The constants at the indices 0, 1 of each round are added, those at index 2 are not.
+        let sum = |index: u64| Fr::from((0..5u64).map(|round| 3 * round + index + 1).sum::<u64>());
+        assert_eq!(state, [sum(0), sum(1), Fr::zero()]);
+        assert!(crate::configurations::optimized_constants::derive_optimized_constants::<Fr, 3, RateConstantsConfig>().is_none());
+    }
+
+    #[test]
+    fn test_permutation_fn() {
+        /// Applies a permutation twice, as an example of a combinator.
+        fn twice(permutation: impl Fn(&mut [Fr; 3])) -> impl Fn(&mut [Fr; 3]) {
+            move |state| {
+                permutation(state);
+                permutation(state);
+            }
+        }
+        let permutation = permutation_fn::<Fr, 3, ConfigX5_254_3>();
+        let mut state = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut expected = state;
+        permutation(&mut state);
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        assert_eq!(state, expected);
+
+        twice(permutation_fn::<Fr, 3, ConfigX5_254_3>())(&mut state);
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_perm_auto() {
+        for n in 0..5u64 {
+            let mut dense = [Fr::from(n), Fr::from(2 * n), Fr::from(3 * n + 1)];
+            let mut auto = dense;
+            perm::<Fr, 3, ConfigX5_254_3>(&mut dense);
+            assert_eq!(perm_auto::<Fr, 3, ConfigX5_254_3>(&mut auto), LinearLayerPath::Sparse);
+            assert_eq!(auto, dense);
+        }
+
+        // Configs without optimized constants use the dense path.
+        let mut input = [Fr::one(), Fr::from(10), Fr::from(100)];
+        assert_eq!(perm_auto::<Fr, 3, MatrixConfig>(&mut input), LinearLayerPath::Dense);
+        assert_eq!(input, [Fr::from(200), Fr::from(4), Fr::from(40)]);
+    }
+
+    #[test]
+    fn test_perm_trace() {
+        let input = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        let trace = perm_trace::<Fr, 3, ConfigX5_254_3>(input);
+        assert_eq!(trace.len(), 65);
+        let mut expected = input;
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        assert_eq!(trace.last(), Some(&expected));
+
+        // The first entry is the state after the first full round.
+        let mut first_round = input;
+        for (word, constant) in first_round.iter_mut().zip(ConfigX5_254_3::round_constants()) {
+            *word = sbox_pow5(&(*word + constant));
+        }
+        assert_eq!(trace[0], matrix_vector_mul(ConfigX5_254_3::mds_matrix(), &first_round));
+    }
+
+    #[test]
+    fn test_perm_trace_circom() {
+        let input = [Fr::from(7), Fr::zero(), Fr::zero()];
+        let dump = perm_trace_circom::<Fr, 3, ConfigX5_254_3>(input);
+        let elements: Vec<Fr> = dump
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .map(|element| element.trim().trim_matches('"').parse().unwrap())
+            .collect();
+        assert_eq!(elements.len(), 3 * 66);
+        assert!(dump.starts_with("[\n \"7\",\n \"0\",\n \"0\",\n"));
+
+        // The rate-1 sponge hash of a single element is the first element of the final state.
+        assert_eq!(elements[3 * 65], crate::x5_254_3::hash(&[Fr::from(7)])[0]);
+        assert_eq!(elements[3 * 65..], perm_trace::<Fr, 3, ConfigX5_254_3>(input)[64]);
+    }
+
+    #[test]
+    fn test_apply_rounds() {
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut expected = input;
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+
+        let mut state = input;
+        let offset = apply_full_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 4, 0);
+        assert_eq!(offset, 12);
+        let offset = apply_partial_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 57, offset);
+        assert_eq!(offset, 183);
+        let offset = apply_full_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 4, offset);
+        assert_eq!(offset, ConfigX5_254_3::round_constants().len());
+        assert_eq!(state, expected);
+
+        // The phases match the trace of the permutation.
+        let trace = perm_trace::<Fr, 3, ConfigX5_254_3>(input);
+        let mut state = input;
+        apply_full_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 4, 0);
+        assert_eq!(state, trace[3]);
+        apply_partial_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 1, 12);
+        assert_eq!(state, trace[4]);
+        assert_eq!(apply_partial_rounds::<Fr, 3, ConfigX5_254_3>(&mut state, 0, 15), 15);
+        assert_eq!(state, trace[4]);
+    }
+
+    #[test]
+    fn test_transpose_matrix() {
+        let m = [[Fr::from(1), Fr::from(2)], [Fr::from(3), Fr::from(4)]];
+        assert_eq!(transpose_matrix(&m), [[Fr::from(1), Fr::from(3)], [Fr::from(2), Fr::from(4)]]);
+        assert_eq!(transpose_matrix(&transpose_matrix(&m)), m);
+
+        let mut expected = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+        let mut column_major = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        perm::<Fr, 3, ColumnMajorX5_254_3<false>>(&mut column_major);
+        assert_ne!(column_major, expected);
+        let mut transposed = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        perm::<Fr, 3, ColumnMajorX5_254_3<true>>(&mut transposed);
+        assert_eq!(transposed, expected);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_trace_round_events() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tracing::{span, Event, Metadata, Subscriber};
+
+        /// A subscriber which only counts the events it receives.
+        struct CountingSubscriber(Arc<AtomicUsize>);
+        impl Subscriber for CountingSubscriber {
+            fn enabled(&self, _metadata: &Metadata<'_>) -> bool { true }
+            fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id { span::Id::from_u64(1) }
+            fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+            fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+            fn event(&self, _event: &Event<'_>) { self.0.fetch_add(1, Ordering::SeqCst); }
+            fn enter(&self, _span: &span::Id) {}
+            fn exit(&self, _span: &span::Id) {}
+        }
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut input = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        tracing::subscriber::with_default(CountingSubscriber(count.clone()), || {
+            perm::<Fr, 3, ConfigX5_254_3>(&mut input);
+        });
+        assert_eq!(count.load(Ordering::SeqCst), ConfigX5_254_3::R_F + ConfigX5_254_3::R_P);
+    }
+
+    /// A synthetic parameter set in Neptune's format, with `T = 3`, `R_F = 2` and `R_P = 1`.
+    /// The MDS matrix `[[3, 2, 1], [1, 1, 0], [4, 0, 1]]` already has the sparse form, so it is
+    /// used as pre-sparse and sparse matrix as well (written transposed, as in Neptune).
+    const SYNTHETIC_NEPTUNE_CONSTANTS: &str = "
+        [full_rounds]
+        2
+        [partial_rounds]
+        1
+        [compressed_round_constants]
+        01 02 03
+        04 05 06
+        07
+        [mds_matrix]
+        03 01 04
+        02 01 00
+        01 00 01
+        [pre_sparse_matrix]
+        03 01 04
+        02 01 00
+        01 00 01
+        [sparse_matrices]
+        03 02 01 01 04
+    ";
+
+    /// The unoptimized equivalent of the synthetic parameter set. The constants of a round are
+    /// the compressed constants added after the previous round, multiplied by the MDS matrix.
+    struct SyntheticDenseConfig;
+    impl PoseidonConfig<Fr, 3> for SyntheticDenseConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 1;
+        const ALPHA: u64 = 5;
+
+        fn mds_matrix() -> &'static [[Fr; 3]; 3] {
+            static MDS_MATRIX: Lazy<[[Fr; 3]; 3]> = Lazy::new(|| {
+                [[Fr::from(3), Fr::from(2), Fr::from(1)],
+                 [Fr::from(1), Fr::from(1), Fr::from(0)],
+                 [Fr::from(4), Fr::from(0), Fr::from(1)]]
+            });
+            &MDS_MATRIX
+        }
+
+        fn round_constants() -> &'static [Fr] {
+            static ROUND_CONSTANTS: Lazy<Vec<Fr>> = Lazy::new(|| {
+                let matrix = SyntheticDenseConfig::mds_matrix();
+                let mut constants = vec![Fr::from(1), Fr::from(2), Fr::from(3)];
+                constants.extend(matrix_vector_mul(matrix, &[Fr::from(4), Fr::from(5), Fr::from(6)]));
+                constants.extend(matrix_vector_mul(matrix, &[Fr::from(7), Fr::zero(), Fr::zero()]));
+                constants
+            });
+            &ROUND_CONSTANTS
+        }
+    }
+
+    static SYNTHETIC_OPTIMIZED_CONSTANTS: Lazy<OptimizedConstants<Fr, 3>> = Lazy::new(|| {
+        import_neptune_constants(SYNTHETIC_NEPTUNE_CONSTANTS).unwrap()
+    });
+
+    struct SyntheticDensePermutation;
+    impl Permutation<Fr, 3> for SyntheticDensePermutation {
+        fn apply(state: &mut [Fr; 3]) {
+            perm::<Fr, 3, SyntheticDenseConfig>(state);
+        }
+    }
+
+    struct SyntheticOptimizedPermutation;
+    impl Permutation<Fr, 3> for SyntheticOptimizedPermutation {
+        fn apply(state: &mut [Fr; 3]) {
+            perm_optimized(state, &SYNTHETIC_OPTIMIZED_CONSTANTS);
+        }
+    }
+
+    #[test]
+    fn test_import_neptune_constants() {
+        let constants = &SYNTHETIC_OPTIMIZED_CONSTANTS;
+        assert_eq!(constants.mds_matrix, *SyntheticDenseConfig::mds_matrix());
+        assert_eq!(constants.sparse_matrices.len(), 1);
+        assert_eq!(constants.compressed_round_constants.len(), 7);
+
+        let mut dense = [Fr::from(5), Fr::from(6), Fr::from(7)];
+        let mut optimized = dense;
+        perm::<Fr, 3, SyntheticDenseConfig>(&mut dense);
+        perm_optimized(&mut optimized, constants);
+        assert_eq!(dense, optimized);
+
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let dense_hash = Sponge::<Fr, SyntheticDensePermutation, 2, 3>::new([Fr::zero(); 3]).hash(&input, 1);
+        let optimized_hash = Sponge::<Fr, SyntheticOptimizedPermutation, 2, 3>::new([Fr::zero(); 3]).hash(&input, 1);
+        assert_eq!(dense_hash, optimized_hash);
+    }
+
+    /// A config over `F101`, where `x^5` is not a permutation, so it uses `x^3`.
+    struct SmallFieldConfig;
+    impl PoseidonConfig<F101, 2> for SmallFieldConfig {
+        const R_F: usize = 2;
+        const R_P: usize = 1;
+        const ALPHA: u64 = 3;
+
+        fn mds_matrix() -> &'static [[F101; 2]; 2] {
+            static IDENTITY_MATRIX: Lazy<[[F101; 2]; 2]> = Lazy::new(|| {
+                parse_matrix(["01 00", "00 01"])
+            });
+            &IDENTITY_MATRIX
+        }
+
+        fn round_constants() -> &'static [F101] {
+            static ZEROES: Lazy<Vec<F101>> = Lazy::new(|| parse_constants("00\n00\n00\n00\n00\n00"));
+            &ZEROES
+        }
+    }
+
+    #[test]
+    fn test_small_field() {
+        // With the identity matrix and zero constants, state[0] is cubed in all three rounds,
+        // and state[1] only in the two full rounds.
+        let mut state = [F101::from(2), F101::from(3)];
+        perm::<F101, 2, SmallFieldConfig>(&mut state);
+        assert_eq!(state, [F101::from(2).pow([27]), F101::from(3).pow([9])]);
+        assert_eq!(F101::from(2).pow([27]), F101::from(40));
+
+        struct SmallFieldPermutation;
+        impl Permutation<F101, 2> for SmallFieldPermutation {
+            fn apply(state: &mut [F101; 2]) {
+                perm::<F101, 2, SmallFieldConfig>(state);
+            }
+        }
+        let mut sponge = Sponge::<F101, SmallFieldPermutation, 1, 2>::new([F101::zero(); 2]);
+        assert_eq!(sponge.hash(&[F101::from(2)], 1), vec![F101::from(40)]);
+    }
+
+    /// A deliberately naive reference implementation of the Poseidon permutation, written
+    /// directly from the specification with `Vec`s and explicit loops, for differential
+    /// testing of `perm` and its optimized variants.
+    #[allow(clippy::needless_range_loop)]
+    fn reference_perm<P: PoseidonConfig<Fr, 3>>(input: &[Fr]) -> Vec<Fr> {
+        let t = input.len();
+        let matrix: Vec<Vec<Fr>> = P::mds_matrix().iter().map(|row| row.to_vec()).collect();
+        let constants: Vec<Fr> = P::round_constants().to_vec();
+        let mut state = input.to_vec();
+        for round in 0..(P::R_F + P::R_P) {
+            for i in 0..t {
+                state[i] += constants[round * t + i];
+            }
+            let is_full_round = round < P::R_F / 2 || round >= P::R_F / 2 + P::R_P;
+            for i in 0..t {
+                if is_full_round || i == 0 {
+                    let x = state[i];
+                    let mut power = Fr::one();
+                    for _ in 0..P::ALPHA {
+                        power *= x;
+                    }
+                    state[i] = power;
+                }
+            }
+            let mut next = vec![Fr::zero(); t];
+            for i in 0..t {
+                for j in 0..t {
+                    next[i] += matrix[i][j] * state[j];
+                }
+            }
+            state = next;
+        }
+        state
+    }
+
+    #[test]
+    fn test_matches_reference_permutation() {
+        use ark_std::{test_rng, UniformRand};
+        let mut rng = test_rng();
+        for _ in 0..20 {
+            let input = [Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)];
+            let expected = reference_perm::<ConfigX5_254_3>(&input);
+            let mut state = input;
+            perm::<Fr, 3, ConfigX5_254_3>(&mut state);
+            assert_eq!(state.to_vec(), expected);
+            let mut state = input;
+            perm_auto::<Fr, 3, ConfigX5_254_3>(&mut state);
+            assert_eq!(state.to_vec(), expected);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,298 @@
+//! This file implements the SAFE (Sponge API for Field Elements) construction on top of a
+//! customizable permutation function.
+//!
+//! In contrast to the plain `Sponge`, a `SafeSponge` is created together with an IO pattern,
+//! i.e. the exact sequence of absorb and squeeze calls which will be made. The IO pattern
+//! and a domain separator are hashed into a tag, which is placed in the capacity of the
+//! initial state. Any call which does not follow the IO pattern results in an error, which
+//! protects against accidental misuse of the sponge.
+//!
+//! The tag is computed as in the SAFE specification: consecutive calls of the same kind are
+//! aggregated, each call is encoded as a 32-bit word (with the most significant bit set for
+//! absorb calls), the words are serialized as big-endian bytes, the domain separator is
+//! appended, and the first 128 bits of the SHA3-256 hash of the result are interpreted as a
+//! big-endian integer.
+//!
+//! Note that, as for `Sponge`, the rate is the first `RATE` elements of the state, and the
+//! capacity is the remaining `N - RATE` elements. The tag is placed in the first capacity
+//! element.
+
+use ark_ff::PrimeField;
+use sha3::{Digest, Sha3_256};
+
+use crate::sponge::{Permutation, is_valid_rate};
+
+/// A single call in an IO pattern, together with the number of elements it concerns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoCall {
+    Absorb(u32),
+    Squeeze(u32),
+}
+
+impl IoCall {
+    /// The number of elements absorbed or squeezed by the call.
+    fn len(&self) -> u32 {
+        match self {
+            IoCall::Absorb(len) | IoCall::Squeeze(len) => *len,
+        }
+    }
+
+    /// The 32-bit word encoding the call in the tag computation.
+    fn encode(&self) -> u32 {
+        match self {
+            IoCall::Absorb(len) => 0x8000_0000 | len,
+            IoCall::Squeeze(len) => *len,
+        }
+    }
+}
+
+/// The errors which can occur when using a `SafeSponge`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SafeError {
+    /// The call does not follow the IO pattern of the sponge.
+    IoPatternViolation,
+    /// `finish` was called before all calls in the IO pattern had been made.
+    IoPatternNotCompleted,
+}
+
+/// Merges consecutive calls of the same kind into a single call, and removes empty calls.
+fn aggregate_io_pattern(io_pattern: &[IoCall]) -> Vec<IoCall> {
+    let mut aggregated: Vec<IoCall> = Vec::new();
+    for call in io_pattern.iter().filter(|call| call.len() > 0) {
+        match (aggregated.last_mut(), call) {
+            (Some(IoCall::Absorb(total)), IoCall::Absorb(len))
+            | (Some(IoCall::Squeeze(total)), IoCall::Squeeze(len)) => {
+                *total = total.checked_add(*len).expect("IO pattern call length overflows u32");
+            }
+            _ => aggregated.push(*call),
+        }
+    }
+    for call in &aggregated {
+        assert!(call.len() < 0x8000_0000, "IO pattern call lengths must be smaller than 2^31");
+    }
+    aggregated
+}
+
+/// Encodes an (aggregated) IO pattern as big-endian 32-bit words followed by the domain
+/// separator.
+fn encode_io_pattern(io_pattern: &[IoCall], domain_separator: &[u8]) -> Vec<u8> {
+    io_pattern
+        .iter()
+        .flat_map(|call| call.encode().to_be_bytes())
+        .chain(domain_separator.iter().copied())
+        .collect()
+}
+
+/// Computes the 128-bit tag of an IO pattern and a domain separator, as a field element.
+fn io_pattern_tag<F: PrimeField>(io_pattern: &[IoCall], domain_separator: &[u8]) -> F {
+    let digest = Sha3_256::digest(encode_io_pattern(&aggregate_io_pattern(io_pattern), domain_separator));
+    F::from_be_bytes_mod_order(&digest[..16])
+}
+
+/// A SafeSponge is a sponge which only accepts calls following a fixed IO pattern.
+///
+/// It is parameterized in the same way as `Sponge`, by the field type `F`, the permutation
+/// function `P`, the rate `RATE` and the state size `N`. Since the tag lives in the capacity,
+/// we require `0 < RATE < N`, which is checked at compile time.
+///
+/// In contrast with `Sponge`, inputs and outputs may have any length. Permutations are only
+/// applied when needed, i.e. when absorbing into a full rate, or when squeezing after absorbing
+/// or after the rate has been read completely.
+pub struct SafeSponge<F: PrimeField, P: Permutation<F, N>, const RATE: usize, const N: usize> {
+    state: [F; N],
+    absorb_pos: usize,
+    squeeze_pos: usize,
+    io_pattern: Vec<IoCall>,
+    io_index: usize,
+    io_consumed: u32,
+    _marker: std::marker::PhantomData<fn() -> P>,
+}
+
+impl<F: PrimeField, P: Permutation<F, N>, const RATE: usize, const N: usize> SafeSponge<F, P, RATE, N> {
+
+    /// Creates a new SafeSponge for the given IO pattern and domain separator (the SAFE
+    /// `START` call).
+    pub fn start(io_pattern: &[IoCall], domain_separator: &[u8]) -> Self {
+        const { assert!(is_valid_rate(RATE, N), "RATE must be positive and smaller than N") };
+        let mut state = [F::zero(); N];
+        state[RATE] = io_pattern_tag(io_pattern, domain_separator);
+        SafeSponge::<F, P, RATE, N> {
+            state,
+            absorb_pos: 0,
+            squeeze_pos: 0,
+            io_pattern: aggregate_io_pattern(io_pattern),
+            io_index: 0,
+            io_consumed: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Absorbs the input into the sponge state (the SAFE `ABSORB` call).
+    pub fn absorb(&mut self, input: &[F]) -> Result<(), SafeError> {
+        let len = u32::try_from(input.len()).map_err(|_| SafeError::IoPatternViolation)?;
+        self.record_call(IoCall::Absorb(len))?;
+        // An empty call is a no-op, and must not force a permutation before the next squeeze.
+        if input.is_empty() {
+            return Ok(());
+        }
+        for x in input {
+            if self.absorb_pos == RATE {
+                P::apply(&mut self.state);
+                self.absorb_pos = 0;
+            }
+            self.state[self.absorb_pos] += x;
+            self.absorb_pos += 1;
+        }
+        self.squeeze_pos = RATE;
+        Ok(())
+    }
+
+    /// Squeezes `length` elements from the sponge state (the SAFE `SQUEEZE` call).
+    pub fn squeeze(&mut self, length: usize) -> Result<Vec<F>, SafeError> {
+        let len = u32::try_from(length).map_err(|_| SafeError::IoPatternViolation)?;
+        self.record_call(IoCall::Squeeze(len))?;
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let mut output = Vec::with_capacity(length);
+        for _ in 0..length {
+            if self.squeeze_pos == RATE {
+                P::apply(&mut self.state);
+                self.squeeze_pos = 0;
+            }
+            output.push(self.state[self.squeeze_pos]);
+            self.squeeze_pos += 1;
+        }
+        self.absorb_pos = RATE;
+        Ok(output)
+    }
+
+    /// Finishes the sponge (the SAFE `FINISH` call). Returns an error if the IO pattern
+    /// has not been completed.
+    pub fn finish(mut self) -> Result<(), SafeError> {
+        self.state = [F::zero(); N];
+        if self.io_index != self.io_pattern.len() {
+            return Err(SafeError::IoPatternNotCompleted);
+        }
+        Ok(())
+    }
+
+    /// Checks that the call follows the IO pattern, and advances the position in the pattern.
+    /// On a violation, the state is erased.
+    fn record_call(&mut self, call: IoCall) -> Result<(), SafeError> {
+        if call.len() == 0 {
+            return Ok(());
+        }
+        let total = match (call, self.io_pattern.get(self.io_index)) {
+            (IoCall::Absorb(_), Some(IoCall::Absorb(total)))
+            | (IoCall::Squeeze(_), Some(IoCall::Squeeze(total))) => *total,
+            _ => 0,
+        };
+        if self.io_consumed + call.len() > total {
+            self.state = [F::zero(); N];
+            return Err(SafeError::IoPatternViolation);
+        }
+        self.io_consumed += call.len();
+        if self.io_consumed == total {
+            self.io_index += 1;
+            self.io_consumed = 0;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use crate::x5_254_3::PermX5_254_3;
+
+    type TestSponge = SafeSponge<Fr, PermX5_254_3, 2, 3>;
+
+    #[test]
+    fn test_encode_io_pattern() {
+        // The example from the SAFE specification: a 2-to-1 compression absorbs two elements
+        // (possibly in two calls) and squeezes one.
+        let io_pattern = aggregate_io_pattern(&[IoCall::Absorb(1), IoCall::Absorb(1), IoCall::Squeeze(1)]);
+        assert_eq!(io_pattern, vec![IoCall::Absorb(2), IoCall::Squeeze(1)]);
+        let encoded = encode_io_pattern(&io_pattern, &[0x42]);
+        assert_eq!(encoded, vec![0x80, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x42]);
+    }
+
+    #[test]
+    fn test_tag_depends_on_pattern_and_domain() {
+        let tag_1: Fr = io_pattern_tag(&[IoCall::Absorb(2), IoCall::Squeeze(1)], b"domain");
+        let tag_2: Fr = io_pattern_tag(&[IoCall::Absorb(1), IoCall::Absorb(1), IoCall::Squeeze(1)], b"domain");
+        let tag_3: Fr = io_pattern_tag(&[IoCall::Absorb(2), IoCall::Squeeze(2)], b"domain");
+        let tag_4: Fr = io_pattern_tag(&[IoCall::Absorb(2), IoCall::Squeeze(1)], b"other domain");
+        assert_eq!(tag_1, tag_2);
+        assert_ne!(tag_1, tag_3);
+        assert_ne!(tag_1, tag_4);
+    }
+
+    #[test]
+    fn test_follow_io_pattern() {
+        let io_pattern = [IoCall::Absorb(1), IoCall::Absorb(1), IoCall::Squeeze(1)];
+        let mut sponge = TestSponge::start(&io_pattern, b"merkle");
+        sponge.absorb(&[Fr::from(1), Fr::from(2)]).unwrap();
+        let output_1 = sponge.squeeze(1).unwrap();
+        sponge.finish().unwrap();
+
+        // Splitting the absorb differently gives the same result.
+        let mut sponge = TestSponge::start(&io_pattern, b"merkle");
+        sponge.absorb(&[Fr::from(1)]).unwrap();
+        sponge.absorb(&[Fr::from(2)]).unwrap();
+        let output_2 = sponge.squeeze(1).unwrap();
+        sponge.finish().unwrap();
+        assert_eq!(output_1, output_2);
+
+        // Another domain separator gives another result.
+        let mut sponge = TestSponge::start(&io_pattern, b"other");
+        sponge.absorb(&[Fr::from(1), Fr::from(2)]).unwrap();
+        let output_3 = sponge.squeeze(1).unwrap();
+        sponge.finish().unwrap();
+        assert_ne!(output_1, output_3);
+    }
+
+    #[test]
+    fn test_violate_io_pattern() {
+        let io_pattern = [IoCall::Absorb(2), IoCall::Squeeze(1)];
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        assert_eq!(sponge.absorb(&[Fr::from(1), Fr::from(2), Fr::from(3)]), Err(SafeError::IoPatternViolation));
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        assert_eq!(sponge.squeeze(1), Err(SafeError::IoPatternViolation));
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        sponge.absorb(&[Fr::from(1), Fr::from(2)]).unwrap();
+        assert_eq!(sponge.finish(), Err(SafeError::IoPatternNotCompleted));
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        sponge.absorb(&[Fr::from(1), Fr::from(2)]).unwrap();
+        sponge.squeeze(1).unwrap();
+        assert_eq!(sponge.squeeze(1), Err(SafeError::IoPatternViolation));
+    }
+
+    #[test]
+    fn test_empty_calls_are_no_ops() {
+        let io_pattern = [IoCall::Absorb(2), IoCall::Squeeze(2)];
+        let input = [Fr::from(1), Fr::from(2)];
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        sponge.absorb(&input).unwrap();
+        let expected = [sponge.squeeze(1).unwrap(), sponge.squeeze(1).unwrap()];
+
+        let mut sponge = TestSponge::start(&io_pattern, b"");
+        sponge.absorb(&[]).unwrap();
+        sponge.absorb(&input).unwrap();
+        sponge.squeeze(0).unwrap();
+        let first = sponge.squeeze(1).unwrap();
+        sponge.absorb(&[]).unwrap();
+        assert_eq!(sponge.squeeze(0).unwrap(), vec![]);
+        let second = sponge.squeeze(1).unwrap();
+        assert_eq!([first, second], expected);
+        sponge.finish().unwrap();
+    }
+}
@@ -6,7 +6,10 @@
 //! and input. In particular, this could enable one to use the construction for more
 //! general types of hash function, not just Poseidon.
 
-use ark_ff::Field;
+use std::borrow::BorrowMut;
+use std::marker::PhantomData;
+use ark_ec::AffineRepr;
+use ark_ff::{Field, PrimeField, BigInteger};
 
 /// A struct which satisfies the Permutation trait can be used as a permutation function
 /// for the Sponge construction.
@@ -18,57 +21,539 @@ pub trait Permutation<F : Field, const N: usize> {
 }
 
 
+/// Checks that `rate` is a valid rate for a sponge with a state of size `n`, i.e. that
+/// `1 <= rate < n`, so that at least one element is absorbed per permutation and at least
+/// one capacity element remains. Since this is a `const fn`, it can be used in compile-time
+/// assertions.
+pub const fn is_valid_rate(rate: usize, n: usize) -> bool {
+    rate >= 1 && rate < n
+}
+
+
+/// How a Sponge absorbs input into its rate.
+/// 
+/// In the standard `Add` mode, each input element is added to a rate element. In the
+/// `Overwrite` mode, the rate element is replaced by the input element instead. Both modes are
+/// secure sponge constructions (the overwrite mode has been shown to be as secure as the
+/// additive one), but they are not interchangeable: the same input gives different outputs,
+/// so both parties must agree on the mode. Note that in the overwrite mode, the previous rate
+/// content is lost, and that a trailing partial block only overwrites the first elements of
+/// the rate, so that the remaining rate elements keep their previous (permuted) values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbsorbMode {
+    Add,
+    Overwrite,
+}
+
+/// The errors which can occur when misusing a sponge through its fallible methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpongeError {
+    /// The sponge has been finished with `squeeze_final`, so no more input may be absorbed.
+    AbsorbAfterFinalSqueeze,
+}
+
+
 /// A Sponge is the the state keeping structure of a sponge construction.
 ///  
 /// It is parameterized by the field type `F`, the permutation function `P`, the rate `RATE`
-/// and the capacity `N`. Note that we require `Rate <= N` (unfortunately, this is enforced
-/// only at runtime).
+/// and the state size `N`. Note that we require `0 < RATE < N`, which is checked at compile
+/// time.
 /// 
 /// A Sponge can do two things: absorb input and squeeze output. After each input or output,
 /// the state is permuted, to ensure that the input should be unguessable from the output.
-pub struct Sponge<F: Field, P: Permutation<F,N>, const RATE: usize, const N: usize> {
-    state: [F; N],
-    _marker: std::marker::PhantomData<P>,
+/// 
+/// Input may also be absorbed element by element, in which case the elements are buffered
+/// in the rate until a full block of `RATE` elements has been absorbed, at which point the
+/// state is permuted. By default, input is added to the rate, see `AbsorbMode` and
+/// `new_with_mode` for the overwrite mode.
+/// 
+/// The padding is implicit zero padding: a trailing partial block is completed with zeroes
+/// when squeezing, while an input whose length is an exact multiple of `RATE` gets no extra
+/// padding block, since its last block has already been permuted when it was absorbed. This
+/// is the behavior of the published `x5_254_3::hash` digests. As a consequence, inputs which
+/// only differ by trailing zeroes within the last block (e.g. `[a]` and `[a, 0]` with rate 2)
+/// are absorbed identically, so the input length should be fixed or encoded (see e.g.
+/// `x5_254_3::hash_roots`) if this matters.
+/// 
+/// Only the rate is ever read by `squeeze` and `squeeze_final`, so the output never reveals the
+/// capacity. In particular, a digest does not allow one to continue absorbing where the hash
+/// left off, since this requires the full state. Hence, unlike Merkle-Damgard hashes, the
+/// sponge is not vulnerable to length-extension attacks (provided that `N - RATE` elements
+/// give enough security for the application).
+/// 
+/// By default, a Sponge owns its state. The last parameter `S` allows the state to be stored
+/// elsewhere, e.g. as a mutable borrow (see `from_state_mut`).
+/// 
+/// Since the permutation is only used through its associated function, a Sponge is `Send`
+/// and `Sync` whenever its state is, regardless of the permutation type.
+pub struct Sponge<F: Field, P: Permutation<F,N>, const RATE: usize, const N: usize, S: BorrowMut<[F; N]> = [F; N]> {
+    state: S,
+    absorb_pos: usize,
+    mode: AbsorbMode,
+    finished: bool,
+    /// The block cached by `squeeze_single`, and the position of the next element to read
+    /// from it. A position of `RATE` means that no block is cached. Every method which
+    /// changes the state sets the position to `RATE`, so that the cache is never stale.
+    squeeze_block: [F; RATE],
+    squeeze_pos: usize,
+    /// The number of elements squeezed so far, only tracked in debug builds.
+    #[cfg(debug_assertions)]
+    squeezed: usize,
+    _marker: PhantomData<(F, fn() -> P)>,
 }
 
 impl<F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Sponge<F, P, RATE, N> {
 
     /// Creates a new Sponge with the given initial state.
     pub fn new(start_state: [F; N]) -> Self {
-        assert!(RATE <= N, "RATE must be less than or equal to N");
+        const { assert!(is_valid_rate(RATE, N), "RATE must be positive and smaller than N") };
         Sponge::<F, P, RATE, N> {
             state: start_state,
-            _marker: std::marker::PhantomData,
+            absorb_pos: 0,
+            mode: AbsorbMode::Add,
+            finished: false,
+            squeeze_block: [F::zero(); RATE],
+            squeeze_pos: RATE,
+            #[cfg(debug_assertions)]
+            squeezed: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new Sponge with the given initial state, which absorbs input in the given mode.
+    pub fn new_with_mode(start_state: [F; N], mode: AbsorbMode) -> Self {
+        let mut sponge = Self::new(start_state);
+        sponge.mode = mode;
+        sponge
+    }
+
+    /// Creates a new Sponge whose initial state encodes the rate, for domain separation
+    /// between sponges of different rates. The first capacity element, `state[RATE]`, is
+    /// set to `RATE * 2^64` (i.e. `(RATE as u128) << 64`), and all other elements are zero.
+    /// 
+    /// As for all sponges, `RATE < N` is checked at compile time, so there is always a
+    /// capacity element to hold the tag.
+    pub fn new_for_rate() -> Self {
+        let mut start_state = [F::zero(); N];
+        start_state[RATE] = F::from((RATE as u128) << 64);
+        Self::new(start_state)
+    }
+
+    /// Resets the sponge to the given initial state, discarding everything absorbed so far,
+    /// so that it can be reused for another hash. The absorb mode is kept.
+    pub fn reset(&mut self, start_state: [F; N]) {
+        self.state = start_state;
+        self.absorb_pos = 0;
+        self.finished = false;
+        self.squeeze_pos = RATE;
+        #[cfg(debug_assertions)]
+        {
+            self.squeezed = 0;
+        }
+    }
+}
+
+impl<F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Default for Sponge<F, P, RATE, N> {
+
+    /// Creates a new Sponge with the canonical, domain-separated initial state of
+    /// `new_for_rate`. As there, `RATE < N` is checked at compile time, so that
+    /// `Sponge::default()` never panics.
+    fn default() -> Self {
+        Self::new_for_rate()
+    }
+}
+
+impl<F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Clone for Sponge<F, P, RATE, N> {
+
+    /// Clones the sponge, including any partially absorbed block, so that a common prefix can
+    /// be absorbed once and then continued independently in several branches. This is
+    /// implemented by hand, since a derived implementation would require `P: Clone`.
+    fn clone(&self) -> Self {
+        Sponge::<F, P, RATE, N> {
+            state: self.state,
+            absorb_pos: self.absorb_pos,
+            mode: self.mode,
+            finished: self.finished,
+            squeeze_block: self.squeeze_block,
+            squeeze_pos: self.squeeze_pos,
+            #[cfg(debug_assertions)]
+            squeezed: self.squeezed,
+            _marker: PhantomData,
         }
     }
+}
+
+impl<'a, F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Sponge<F, P, RATE, N, &'a mut [F; N]> {
+
+    /// Creates a new Sponge operating directly on a state owned by the caller, which avoids
+    /// copying the state for very wide configurations.
+    /// 
+    /// The sponge mutably borrows the state for as long as it lives, so the caller can only
+    /// read the resulting state after the sponge has been dropped. Note that a trailing
+    /// partially absorbed block is only permuted when squeezing, so the borrowed state may
+    /// hold unpermuted input if the sponge is dropped before it is squeezed.
+    pub fn from_state_mut(state: &'a mut [F; N]) -> Self {
+        const { assert!(is_valid_rate(RATE, N), "RATE must be positive and smaller than N") };
+        Sponge::<F, P, RATE, N, &'a mut [F; N]> {
+            state,
+            absorb_pos: 0,
+            mode: AbsorbMode::Add,
+            finished: false,
+            squeeze_block: [F::zero(); RATE],
+            squeeze_pos: RATE,
+            #[cfg(debug_assertions)]
+            squeezed: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize, S: BorrowMut<[F; N]>> Sponge<F, P, RATE, N, S> {
+
+    /// The state of the sponge.
+    fn words(&self) -> &[F; N] {
+        self.state.borrow()
+    }
+
+    /// The state of the sponge, mutably.
+    fn words_mut(&mut self) -> &mut [F; N] {
+        self.state.borrow_mut()
+    }
+
+    /// Gives mutable access to the whole sponge state, e.g. for injecting elements into the
+    /// capacity in advanced duplex constructions.
+    /// 
+    /// This bypasses the sponge construction entirely, so it is easy to misuse: writing to
+    /// the capacity based on secret or attacker-controlled data may break the security
+    /// guarantees of the sponge, and a partially absorbed block (which has not been permuted
+    /// yet) is visible in, and may be overwritten through, the rate.
+    pub fn state_mut(&mut self) -> &mut [F; N] {
+        self.squeeze_pos = RATE;
+        self.words_mut()
+    }
+
+    /// The rate of the sponge state, i.e. its first `RATE` elements, which input is absorbed
+    /// into and output is squeezed from. A partially absorbed block is visible here.
+    pub fn rate_slice(&self) -> &[F] {
+        &self.words()[..RATE]
+    }
+
+    /// The capacity of the sponge state, i.e. its last `N - RATE` elements, which are never
+    /// directly touched by absorbing or squeezing.
+    pub fn capacity_slice(&self) -> &[F] {
+        &self.words()[RATE..]
+    }
 
     /// Absorbs `RATE` elements from the input into the sponge state.
     pub fn absorb(&mut self, input: &[F; RATE]) {
-        for i in 0..RATE {
-            self.state[i] += input[i];
+        self.absorb_slice(input);
+    }
+
+    /// Absorbs `RATE` elements as `absorb`, but returns an error instead of absorbing if the
+    /// sponge is used in the wrong phase, i.e. after it has been finished with `squeeze_final`
+    /// (or `hash`).
+    pub fn try_absorb(&mut self, input: &[F; RATE]) -> Result<(), SpongeError> {
+        if self.finished {
+            return Err(SpongeError::AbsorbAfterFinalSqueeze);
+        }
+        self.absorb(input);
+        Ok(())
+    }
+
+    /// Absorbs any number of elements into the sponge state. The state is permuted each time
+    /// a full block of `RATE` elements has been absorbed, so a trailing partial block is kept
+    /// in the rate until more elements are absorbed, or until the sponge is squeezed.
+    /// 
+    /// Panics if any input is given after the sponge has been finished with `squeeze_final`
+    /// (or `hash`), since the input would then silently be lost from the computed digest.
+    /// Use `try_absorb` to get an error instead, or `reset` the sponge to start a new hash.
+    pub fn absorb_slice(&mut self, input: &[F]) {
+        assert!(input.is_empty() || !self.finished, "Cannot absorb into a sponge finished with squeeze_final");
+        if !input.is_empty() {
+            self.squeeze_pos = RATE;
+        }
+        for x in input {
+            let position = self.absorb_pos;
+            match self.mode {
+                AbsorbMode::Add => self.words_mut()[position] += x,
+                AbsorbMode::Overwrite => self.words_mut()[position] = *x,
+            }
+            self.absorb_pos += 1;
+            if self.absorb_pos == RATE {
+                P::apply(self.words_mut());
+                self.absorb_pos = 0;
+            }
+        }
+    }
+
+    /// Absorbs `RATE` elements into the rate like `absorb`, but without permuting the state
+    /// afterwards, so that several blocks can be combined (or the rate otherwise adjusted)
+    /// before an explicit `flush`. This is meant for custom padding or absorption schemes.
+    /// 
+    /// The sponge does not track the deferred permutation: absorbing or squeezing before
+    /// calling `flush` acts on the unpermuted state. Panics if a partial block has been
+    /// absorbed, since the input would then not be aligned with the rate, or if the sponge
+    /// has been finished.
+    pub fn absorb_no_permute(&mut self, input: &[F; RATE]) {
+        assert!(!self.finished, "Cannot absorb into a sponge finished with squeeze_final");
+        assert_eq!(self.absorb_pos, 0, "absorb_no_permute requires the rate to be aligned");
+        self.squeeze_pos = RATE;
+        let mode = self.mode;
+        for (word, x) in self.words_mut()[..RATE].iter_mut().zip(input) {
+            match mode {
+                AbsorbMode::Add => *word += x,
+                AbsorbMode::Overwrite => *word = *x,
+            }
+        }
+    }
+
+    /// Permutes the state, completing an absorption started with `absorb_no_permute`.
+    pub fn flush(&mut self) {
+        self.squeeze_pos = RATE;
+        P::apply(self.words_mut());
+    }
+
+    /// Absorbs a set of key-value pairs (e.g. the entries of a `HashMap`) deterministically:
+    /// the pairs are sorted by key, and the number of pairs followed by each key and its value
+    /// are absorbed. Hence, two maps with the same entries absorb the same elements,
+    /// regardless of the order in which the pairs are given.
+    /// 
+    /// The keys must be unique, since pairs with equal keys would be absorbed in the order in
+    /// which they are given. This is checked in debug builds.
+    pub fn absorb_key_values(&mut self, pairs: impl IntoIterator<Item = (F, F)>) {
+        let mut pairs: Vec<(F, F)> = pairs.into_iter().collect();
+        pairs.sort_by_key(|pair| pair.0);
+        debug_assert!(pairs.windows(2).all(|w| w[0].0 != w[1].0), "The keys must be unique");
+        self.absorb_single(F::from(pairs.len() as u64));
+        for (key, value) in pairs {
+            self.absorb_slice(&[key, value]);
+        }
+    }
+
+    /// Absorbs any number of elements as `absorb_slice`, and returns the sponge, so that calls
+    /// can be chained, e.g. `sponge.absorb_chained(&a).absorb_chained(&b).squeeze()`.
+    pub fn absorb_chained(&mut self, input: &[F]) -> &mut Self {
+        self.absorb_slice(input);
+        self
+    }
+
+    /// Absorbs a single element into the sponge state, buffering it as in `absorb_slice`.
+    /// For a sponge with `RATE = 1`, this is the same as `absorb(&[x])`.
+    pub fn absorb_single(&mut self, x: F) {
+        self.absorb_slice(&[x]);
+    }
+
+    /// Absorbs the input and squeezes `out_len` elements, without resetting the sponge.
+    /// 
+    /// A trailing partial block of the input is padded with zeroes (see `squeeze`), and as
+    /// many blocks as needed are squeezed, with any excess elements of the last block
+    /// discarded. The last block is squeezed with `squeeze_final`, so no permutation follows
+    /// the final read.
+    pub fn hash(&mut self, input: &[F], out_len: usize) -> Vec<F> {
+        self.absorb_slice(input);
+        let mut output = Vec::with_capacity(out_len);
+        while output.len() + RATE < out_len {
+            output.extend_from_slice(&self.squeeze());
+        }
+        if output.len() < out_len {
+            output.extend_from_slice(&self.squeeze_final());
         }
-        P::apply(&mut self.state);
+        self.record_discarded(output.len() - out_len);
+        output.truncate(out_len);
+        output
     }
 
     /// Squeezes `RATE` elements from the sponge state and returns them as an array.
+    /// 
+    /// If a partial block has been absorbed, it is first padded with zeroes and the state
+    /// is permuted, so that the output never contains unpermuted input.
     pub fn squeeze(&mut self) -> [F; RATE] {
-        let output = unsafe {*(self.state[..RATE].as_ptr() as *const [F; RATE])}; 
-        P::apply(&mut self.state);
+        self.pad();
+        self.squeeze_pos = RATE;
+        let output = unsafe {*(self.words()[..RATE].as_ptr() as *const [F; RATE])}; 
+        P::apply(self.words_mut());
+        self.record_squeezed(RATE);
+        output
+    }
+
+    /// Squeezes `n` elements from the sponge state, squeezing as many blocks as needed, with
+    /// any excess elements of the last block discarded. The state is permuted after each
+    /// block, so the sponge may be squeezed again afterwards.
+    /// 
+    /// Squeezing zero elements is a no-op: it returns an empty vector, and neither pads nor
+    /// permutes the state, so generic code may request zero outputs.
+    pub fn squeeze_n(&mut self, n: usize) -> Vec<F> {
+        let mut output = Vec::with_capacity(n);
+        while output.len() < n {
+            output.extend_from_slice(&self.squeeze());
+        }
+        self.record_discarded(output.len() - n);
+        output.truncate(n);
         output
     }
+
+    /// Fills the output with squeezed elements, giving the same elements as
+    /// `squeeze_n(output.len())` without allocating a vector. As there, an empty output is a
+    /// no-op which leaves the state unchanged.
+    pub fn squeeze_into(&mut self, output: &mut [F]) {
+        for chunk in output.chunks_mut(RATE) {
+            let block = self.squeeze();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            self.record_discarded(RATE - chunk.len());
+        }
+    }
+
+    /// Returns an infinite iterator of squeezed elements, e.g. for use as a PRG with
+    /// `take(n)`. The elements are squeezed lazily, one block of `RATE` elements at a time
+    /// with `squeeze`, so taking `n` elements gives the same output as `squeeze_n(n)`, and
+    /// any unused elements of the last block are discarded when the iterator is dropped.
+    pub fn squeeze_stream(&mut self) -> impl Iterator<Item = F> + '_ {
+        let mut block = [F::zero(); RATE];
+        let mut position = RATE;
+        std::iter::from_fn(move || {
+            if position == RATE {
+                block = self.squeeze();
+                // Only the elements which are actually taken count as squeezed.
+                self.record_discarded(RATE);
+                position = 0;
+            }
+            self.record_squeezed(1);
+            position += 1;
+            Some(block[position - 1])
+        })
+    }
+
+    /// Squeezes a single element. A block of `RATE` elements is squeezed with `squeeze` and
+    /// cached in the sponge, and the following calls read from the cached block until it is
+    /// used up, so that consecutive calls give the same output as `squeeze_n`.
+    /// 
+    /// Any change to the state discards the unread elements of the cached block, i.e.
+    /// absorbing input (also with `absorb_no_permute`), `flush`, `state_mut`, `reset`, and
+    /// squeezing a whole block with `squeeze` or `squeeze_final`.
+    pub fn squeeze_single(&mut self) -> F {
+        if self.squeeze_pos == RATE {
+            self.squeeze_block = self.squeeze();
+            // Only the elements which are actually read count as squeezed.
+            self.record_discarded(RATE);
+            self.squeeze_pos = 0;
+        }
+        self.record_squeezed(1);
+        self.squeeze_pos += 1;
+        self.squeeze_block[self.squeeze_pos - 1]
+    }
+
+    /// Squeezes `RATE` elements from the sponge state like `squeeze`, but without permuting
+    /// the state after reading it, which saves a permutation on the last squeeze of a hash.
+    /// 
+    /// Since the state is not permuted, the sponge should not be squeezed again afterwards: a
+    /// following `squeeze` would return the same elements. Note also that if nothing has been
+    /// absorbed, the output is the rate of the initial state. The sponge is considered
+    /// finished afterwards, so that `try_absorb` returns an error until it is `reset`.
+    pub fn squeeze_final(&mut self) -> [F; RATE] {
+        self.pad();
+        self.squeeze_pos = RATE;
+        self.finished = true;
+        self.record_squeezed(RATE);
+        unsafe {*(self.words()[..RATE].as_ptr() as *const [F; RATE])}
+    }
+
+    /// Asserts that exactly `expected` elements have been squeezed since the sponge was
+    /// created (or `reset`), e.g. for checking that a Fiat-Shamir protocol draws the intended
+    /// number of challenges. Every element returned by the squeezing methods counts, while
+    /// the excess elements discarded by e.g. `squeeze_n` do not.
+    /// 
+    /// The count is only tracked in debug builds, and this is a no-op in release builds.
+    pub fn assert_squeezed(&self, expected: usize) {
+        #[cfg(debug_assertions)]
+        assert_eq!(self.squeezed, expected, "Unexpected number of squeezed elements");
+        #[cfg(not(debug_assertions))]
+        let _ = expected;
+    }
+
+    /// Adds to the number of squeezed elements, see `assert_squeezed`.
+    fn record_squeezed(&mut self, elements: usize) {
+        #[cfg(debug_assertions)]
+        {
+            self.squeezed += elements;
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = elements;
+    }
+
+    /// Subtracts squeezed elements which were discarded from the count, see `assert_squeezed`.
+    fn record_discarded(&mut self, elements: usize) {
+        #[cfg(debug_assertions)]
+        {
+            self.squeezed -= elements;
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = elements;
+    }
+
+    /// Completes a partially absorbed block by padding it with zeroes (which amounts to
+    /// leaving the rest of the rate untouched), and permuting the state.
+    fn pad(&mut self) {
+        if self.absorb_pos > 0 {
+            P::apply(self.words_mut());
+            self.absorb_pos = 0;
+        }
+    }
+}
+
+impl<F:Field, P: Permutation<F, N>, const RATE: usize, const N: usize, S: BorrowMut<[F; N]>> Sponge<F, P, RATE, N, S> {
+
+    /// Absorbs an affine curve point over the field `F`, as its coordinates `x` and `y`.
+    /// 
+    /// The point at infinity has no affine coordinates, and is absorbed as `(0, 0)` instead.
+    /// This encoding is unambiguous for short Weierstrass curves `y^2 = x^3 + ax + b` with
+    /// `b != 0` (such as BN254 and BLS12-381), since `(0, 0)` is not on such a curve. For
+    /// twisted Edwards curves, the identity is an affine point, and is absorbed as such.
+    pub fn absorb_point<C: AffineRepr<BaseField = F>>(&mut self, point: &C) {
+        let (x, y) = point.xy().unwrap_or((F::zero(), F::zero()));
+        self.absorb_slice(&[x, y]);
+    }
 }
 
+impl<F: PrimeField, P: Permutation<F, N>, const RATE: usize, const N: usize, S: BorrowMut<[F; N]>> Sponge<F, P, RATE, N, S> {
+
+    /// Squeezes an element of another prime field `G`, e.g. for sampling Fiat-Shamir
+    /// challenges in a field other than the one used by the hash.
+    /// 
+    /// Let `p` be the modulus of `F` and `q` the modulus of `G`. The function squeezes `k`
+    /// elements `f_0, ..., f_{k-1}`, reads them as the digits of the integer
+    /// `X = f_0 + f_1 * p + ... + f_{k-1} * p^(k-1)` and returns `X mod q`. If the squeezed
+    /// elements are uniform, then `X` is uniform in `[0, p^k)`, and the statistical distance
+    /// between the output and a uniform element of `G` is less than `q / p^k`. The number `k`
+    /// is chosen so that this bias is below `2^-128`.
+    pub fn squeeze_to_field<G: PrimeField>(&mut self) -> G {
+        let num_elements = (G::MODULUS_BIT_SIZE + 128).div_ceil(F::MODULUS_BIT_SIZE - 1) as usize;
+        let mut digits = Vec::with_capacity(num_elements);
+        while digits.len() < num_elements {
+            let block = self.squeeze();
+            let needed = (num_elements - digits.len()).min(RATE);
+            self.record_discarded(RATE - needed);
+            digits.extend_from_slice(&block[..needed]);
+        }
+        let modulus = G::from_be_bytes_mod_order(&F::MODULUS.to_bytes_be());
+        digits
+            .iter()
+            .rev()
+            .fold(G::zero(), |acc, digit| acc * modulus + G::from_be_bytes_mod_order(&digit.into_bigint().to_bytes_be()))
+    }
+}
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use ark_bn254::Fr;
-    use ark_ff::{Zero, One};
+    use ark_ff::Zero;
+    use ark_ff::fields::{Fp64, MontBackend, MontConfig};
+    use crate::x5_254_3::PermX5_254_3;
 
     struct IdentityPermutation;
     impl<const N: usize> Permutation<Fr,{N}> for IdentityPermutation {
-        fn apply(state: &mut [Fr; N]) {
+        fn apply(_state: &mut [Fr; N]) {
         }
     }
 
@@ -103,6 +588,60 @@ mod tests {
         assert_eq!(sponge.state, [Fr::from(0x02), Fr::from(0x00), Fr::from(0x00), Fr::from(0x01)]);
     }
 
+    #[test]
+    fn test_absorb_no_permute() {
+        let input = [Fr::from(0x01), Fr::from(0x02)];
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        sponge.absorb_no_permute(&input);
+        assert_eq!(sponge.state, [Fr::from(0x01), Fr::from(0x02), Fr::from(0x00), Fr::from(0x00)]);
+        sponge.flush();
+        let mut expected = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        expected.absorb(&input);
+        assert_eq!(sponge.state, expected.state);
+        assert_eq!(sponge.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_absorb_no_permute_unaligned() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        sponge.absorb_single(Fr::from(1));
+        sponge.absorb_no_permute(&[Fr::from(2), Fr::from(3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot absorb into a sponge finished with squeeze_final")]
+    fn test_absorb_after_squeeze_final() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        let _digest = sponge.hash(&[Fr::from(1), Fr::from(2)], 1);
+        sponge.absorb_single(Fr::from(3));
+    }
+
+    #[test]
+    fn test_try_absorb() {
+        let input = [Fr::from(1), Fr::from(2)];
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        assert_eq!(sponge.try_absorb(&input), Ok(()));
+        let mut expected = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        expected.absorb(&input);
+        assert_eq!(sponge.state, expected.state);
+
+        // Squeezing with `squeeze` does not finish the sponge.
+        sponge.squeeze();
+        assert_eq!(sponge.try_absorb(&input), Ok(()));
+
+        let state = sponge.state;
+        sponge.squeeze_final();
+        assert_eq!(sponge.try_absorb(&input), Err(SpongeError::AbsorbAfterFinalSqueeze));
+        assert_eq!(sponge.state, state);
+
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        sponge.hash(&input, 1);
+        assert_eq!(sponge.try_absorb(&input), Err(SpongeError::AbsorbAfterFinalSqueeze));
+        sponge.reset([Fr::zero(); 4]);
+        assert_eq!(sponge.try_absorb(&input), Ok(()));
+    }
+
     #[test]
     fn test_multiple_absorb() {
         let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
@@ -134,9 +673,428 @@ mod tests {
     }
 
     #[test]
+    fn test_new_for_rate() {
+        let sponge_1 = Sponge::<Fr, IdentityPermutation, 1, 4>::new_for_rate();
+        let sponge_2 = Sponge::<Fr, IdentityPermutation, 2, 4>::new_for_rate();
+        assert_eq!(sponge_1.state, [Fr::zero(), Fr::from(1u128 << 64), Fr::zero(), Fr::zero()]);
+        assert_eq!(sponge_2.state, [Fr::zero(), Fr::zero(), Fr::from(2u128 << 64), Fr::zero()]);
+        assert_ne!(sponge_1.state, sponge_2.state);
+    }
+
+
+    #[test]
+    fn test_absorb_slice() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        sponge.absorb_slice(&[Fr::from(0x01), Fr::from(0x02), Fr::from(0x01)]);
+        sponge.absorb_slice(&[Fr::from(0x04)]);
+        let mut expected = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::zero(); 4]);
+        expected.absorb(&[Fr::from(0x01), Fr::from(0x02)]);
+        expected.absorb(&[Fr::from(0x01), Fr::from(0x04)]);
+        assert_eq!(sponge.state, expected.state);
+    }
+
+    #[test]
+    fn test_absorb_single() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 1, 3>::new([Fr::zero(); 3]);
+        let mut expected = Sponge::<Fr, SimplePermutation, 1, 3>::new([Fr::zero(); 3]);
+        for x in [Fr::from(0x01), Fr::from(0x02), Fr::from(0x03)] {
+            sponge.absorb_single(x);
+            expected.absorb(&[x]);
+            assert_eq!(sponge.state, expected.state);
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let input = [Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04), Fr::from(0x05)];
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::from(0x07); 4]);
+        let output = sponge.hash(&input, 3);
+
+        let mut expected = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::from(0x07); 4]);
+        expected.absorb(&[Fr::from(0x01), Fr::from(0x02)]);
+        expected.absorb(&[Fr::from(0x03), Fr::from(0x04)]);
+        expected.absorb(&[Fr::from(0x05), Fr::zero()]);
+        let first = expected.squeeze();
+        let second = expected.squeeze_final();
+        assert_eq!(output, vec![first[0], first[1], second[0]]);
+        assert_eq!(sponge.state, expected.state);
+    }
+
+    /// A rotation of the state, for any field.
+    struct RotatePermutation;
+    impl<F: Field, const N: usize> Permutation<F, N> for RotatePermutation {
+        fn apply(state: &mut [F; N]) {
+            state.rotate_left(1);
+        }
+    }
+
+    #[test]
+    fn test_absorb_point() {
+        use ark_bn254::{Fq, G1Affine};
+        let generator = G1Affine::generator();
+        let mut sponge = Sponge::<Fq, RotatePermutation, 2, 3>::new([Fq::zero(); 3]);
+        sponge.absorb_point(&generator);
+        // The generator of BN254 is (1, 2).
+        assert_eq!(sponge.state, [Fq::from(2), Fq::zero(), Fq::from(1)]);
+
+        let mut sponge = Sponge::<Fq, RotatePermutation, 2, 3>::new([Fq::zero(); 3]);
+        sponge.absorb_point(&G1Affine::zero());
+        assert_eq!(sponge.state, [Fq::zero(); 3]);
+        sponge.absorb_point(&generator);
+        let mut again = Sponge::<Fq, RotatePermutation, 2, 3>::new([Fq::zero(); 3]);
+        again.absorb_slice(&[Fq::zero(), Fq::zero()]);
+        again.absorb_point(&generator);
+        assert_eq!(sponge.squeeze(), again.squeeze());
+    }
+
+    #[test]
+    fn test_absorb_mode() {
+        let start_state = [Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)];
+        let mut add = Sponge::<Fr, IdentityPermutation, 2, 4>::new_with_mode(start_state, AbsorbMode::Add);
+        let mut overwrite = Sponge::<Fr, IdentityPermutation, 2, 4>::new_with_mode(start_state, AbsorbMode::Overwrite);
+        add.absorb(&[Fr::from(0x10), Fr::from(0x20)]);
+        overwrite.absorb(&[Fr::from(0x10), Fr::from(0x20)]);
+        assert_eq!(add.state, [Fr::from(0x11), Fr::from(0x22), Fr::from(0x03), Fr::from(0x04)]);
+        assert_eq!(overwrite.state, [Fr::from(0x10), Fr::from(0x20), Fr::from(0x03), Fr::from(0x04)]);
+
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut add = Sponge::<Fr, PermX5_254_3, 2, 3>::new_with_mode([Fr::from(1); 3], AbsorbMode::Add);
+        let mut overwrite = Sponge::<Fr, PermX5_254_3, 2, 3>::new_with_mode([Fr::from(1); 3], AbsorbMode::Overwrite);
+        assert_ne!(add.hash(&input, 1), overwrite.hash(&input, 1));
+    }
+
+    #[test]
+    fn test_squeeze_n() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)]);
+        assert_eq!(sponge.squeeze_n(3), vec![Fr::from(0x01), Fr::from(0x02), Fr::from(0x02)]);
+        // Both blocks were permuted, and the excess element of the second block was discarded.
+        assert_eq!(sponge.squeeze_n(1), vec![Fr::from(0x03)]);
+    }
+
+    thread_local! {
+        static PERMUTATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// The x5_254_3 permutation, counting its calls in the current thread.
+    struct CountingPermutation;
+    impl Permutation<Fr, 3> for CountingPermutation {
+        fn apply(state: &mut [Fr; 3]) {
+            PERMUTATIONS.with(|count| count.set(count.get() + 1));
+            PermX5_254_3::apply(state);
+        }
+    }
+
+    /// Returns the output of `f` and the number of permutations it performed.
+    fn count_permutations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        PERMUTATIONS.with(|count| count.set(0));
+        let output = f();
+        (output, PERMUTATIONS.with(|count| count.get()))
+    }
+
+    #[test]
+    fn test_assert_squeezed() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        sponge.assert_squeezed(0);
+        sponge.absorb_slice(&[Fr::from(1)]);
+        sponge.squeeze();
+        sponge.assert_squeezed(2);
+        sponge.squeeze_n(3);
+        sponge.assert_squeezed(5);
+        let _: Vec<Fr> = sponge.squeeze_stream().take(3).collect();
+        sponge.assert_squeezed(8);
+        sponge.hash(&[Fr::from(2)], 1);
+        sponge.assert_squeezed(9);
+        sponge.reset([Fr::zero(); 3]);
+        sponge.assert_squeezed(0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
     #[should_panic]
-    fn test_rate_should_not_be_larger_than_n() {
-        let mut sponge = Sponge::<Fr, SimplePermutation, 5, 4>::new([Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)]);
+    fn test_assert_squeezed_too_many() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        sponge.squeeze();
+        sponge.assert_squeezed(1);
+    }
+
+    #[test]
+    fn test_squeeze_stream() {
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        for n in [0, 1, 2, 5] {
+            let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+            sponge.absorb_slice(&input);
+            let mut expected = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+            expected.absorb_slice(&input);
+            assert_eq!(sponge.squeeze_stream().take(n).collect::<Vec<_>>(), expected.squeeze_n(n));
+            // The sponges continue in the same state.
+            assert_eq!(sponge.squeeze(), expected.squeeze());
+        }
+    }
+
+    #[test]
+    fn test_padding_at_rate_multiple() {
+        let permute = |mut state: [Fr; 3]| {
+            PermX5_254_3::apply(&mut state);
+            state
+        };
+        let zero = Fr::zero();
+        let (a, b, c) = (Fr::from(1), Fr::from(2), Fr::from(3));
+        let hash = |input: &[Fr]| Sponge::<Fr, PermX5_254_3, 2, 3>::new([zero; 3]).hash(input, 1)[0];
+
+        // An exact multiple of the rate is permuted once per block, without an extra block.
+        let exact = permute([a, b, zero]);
+        assert_eq!(hash(&[a, b]), exact[0]);
+        // One element more starts a new block, which is zero padded.
+        let mut above = exact;
+        above[0] += c;
+        assert_eq!(hash(&[a, b, c]), permute(above)[0]);
+        assert_ne!(hash(&[a, b]), hash(&[a, b, c]));
+        // Even a zero element above the multiple costs one more permutation.
+        assert_eq!(hash(&[a, b, zero]), permute(exact)[0]);
+        assert_ne!(hash(&[a, b]), hash(&[a, b, zero]));
+        // But trailing zeroes within the last block are not distinguished.
+        assert_eq!(hash(&[a, b, c]), hash(&[a, b, c, zero]));
+    }
+
+    #[test]
+    fn test_hash_permutation_count() {
+        // Hashing a single element permutes once after absorbing it, and reads the digest
+        // without a further permutation.
+        let (rate_1, count) = count_permutations(|| Sponge::<Fr, CountingPermutation, 1, 3>::new([Fr::zero(); 3]).hash(&[Fr::from(1)], 1));
+        assert_eq!(count, 1);
+        assert_eq!(rate_1, crate::x5_254_3::hash(&[Fr::from(1)]).to_vec());
+
+        // With rate 2, the partial block is padded and permuted once.
+        let (_, count) = count_permutations(|| Sponge::<Fr, CountingPermutation, 2, 3>::new([Fr::zero(); 3]).hash(&[Fr::from(1)], 1));
+        assert_eq!(count, 1);
+
+        // Squeezing two blocks needs one permutation in between.
+        let (_, count) = count_permutations(|| Sponge::<Fr, CountingPermutation, 1, 3>::new([Fr::zero(); 3]).hash(&[Fr::from(1)], 2));
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_state_mut() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 1, 3>::new([Fr::zero(); 3]);
+        sponge.state_mut()[2] = Fr::from(7);
+        sponge.absorb(&[Fr::from(1)]);
+
+        let mut expected = [Fr::from(1), Fr::zero(), Fr::from(7)];
+        PermX5_254_3::apply(&mut expected);
+        assert_eq!(*sponge.state_mut(), expected);
+        assert_eq!(sponge.squeeze_final(), [expected[0]]);
+    }
+
+    #[test]
+    fn test_rate_and_capacity_slices() {
+        let start_state = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4)];
+        let mut sponge = Sponge::<Fr, IdentityPermutation, 3, 4>::new(start_state);
+        assert_eq!(sponge.rate_slice(), &start_state[..3]);
+        assert_eq!(sponge.capacity_slice(), &start_state[3..]);
+
+        sponge.absorb_single(Fr::from(10));
+        assert_eq!(sponge.rate_slice(), &[Fr::from(11), Fr::from(2), Fr::from(3)]);
+        assert_eq!(sponge.capacity_slice(), &[Fr::from(4)]);
+        assert_eq!([sponge.rate_slice(), sponge.capacity_slice()].concat(), sponge.state_mut().to_vec());
+    }
+
+    #[test]
+    fn test_zero_length_squeeze() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut expected = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        // A partial block is neither padded nor permuted by a zero-length squeeze.
+        sponge.absorb_single(Fr::from(1));
+        expected.absorb_single(Fr::from(1));
+        let state = *sponge.state_mut();
+        assert!(sponge.squeeze_n(0).is_empty());
+        sponge.squeeze_into(&mut []);
+        assert_eq!(*sponge.state_mut(), state);
+        sponge.assert_squeezed(0);
+
+        sponge.absorb_single(Fr::from(2));
+        expected.absorb_single(Fr::from(2));
+        assert_eq!(sponge.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_squeeze_into() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut expected = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut output = [Fr::zero(); 5];
+        sponge.squeeze_into(&mut output);
+        assert_eq!(output.to_vec(), expected.squeeze_n(5));
+        sponge.assert_squeezed(5);
+        assert_eq!(sponge.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_absorb_chained() {
+        let a = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let b = [Fr::from(4)];
+        let chained = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]).absorb_chained(&a).absorb_chained(&b).squeeze();
+        let mut expected = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        expected.absorb_slice(&a);
+        expected.absorb_slice(&b);
+        assert_eq!(chained, expected.squeeze());
+    }
+
+    #[test]
+    fn test_reset() {
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new_with_mode([Fr::zero(); 3], AbsorbMode::Overwrite);
+        let expected = sponge.hash(&input, 2);
+        sponge.state_mut()[0] = Fr::from(4);
+        sponge.reset([Fr::zero(); 3]);
+        assert_eq!(sponge.hash(&input, 2), expected);
+    }
+
+    #[test]
+    fn test_absorb_key_values() {
+        use std::collections::HashMap;
+        let pairs: Vec<(Fr, Fr)> = (0..10u64).map(|i| (Fr::from(i * 7 % 10), Fr::from(i))).collect();
+        let map: HashMap<Fr, Fr> = pairs.iter().copied().collect();
+        let mut reversed = pairs.clone();
+        reversed.reverse();
+
+        let digest = |pairs: Vec<(Fr, Fr)>| {
+            let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+            sponge.absorb_key_values(pairs);
+            sponge.squeeze_final()
+        };
+        assert_eq!(digest(pairs.clone()), digest(reversed));
+        assert_eq!(digest(pairs.clone()), digest(map.into_iter().collect()));
+
+        let mut sorted = pairs.clone();
+        sorted.sort();
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        sponge.absorb_single(Fr::from(10));
+        for (key, value) in sorted {
+            sponge.absorb_slice(&[key, value]);
+        }
+        assert_eq!(digest(pairs.clone()), sponge.squeeze_final());
+        assert_ne!(digest(pairs[..9].to_vec()), digest(pairs));
+    }
+
+    #[test]
+    fn test_default() {
+        let sponge = Sponge::<Fr, SimplePermutation, 2, 4>::default();
+        let expected = Sponge::<Fr, SimplePermutation, 2, 4>::new_for_rate();
+        assert_eq!(sponge.state, expected.state);
+        assert_eq!(sponge.absorb_pos, expected.absorb_pos);
+    }
+
+    #[test]
+    fn test_squeeze_reveals_only_rate() {
+        let start_state = [Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)];
+        let mut sponge = Sponge::<Fr, IdentityPermutation, 2, 4>::new(start_state);
+        sponge.absorb_single(Fr::from(0x10));
+        assert_eq!(sponge.squeeze(), [Fr::from(0x11), Fr::from(0x02)]);
+        assert_eq!(sponge.squeeze_final(), [Fr::from(0x11), Fr::from(0x02)]);
+        assert_eq!(sponge.hash(&[], 5), vec![Fr::from(0x11), Fr::from(0x02), Fr::from(0x11), Fr::from(0x02), Fr::from(0x11)]);
+    }
+
+    #[test]
+    fn test_squeeze_single() {
+        let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut cached = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut uncached = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        cached.absorb_slice(&input);
+        uncached.absorb_slice(&input);
+        let expected = uncached.squeeze_n(7);
+        for x in expected {
+            assert_eq!(cached.squeeze_single(), x);
+        }
+        cached.assert_squeezed(7);
+
+        // Absorbing discards the rest of the cached block.
+        cached.absorb_single(Fr::from(4));
+        uncached.absorb_single(Fr::from(4));
+        assert_eq!(cached.squeeze_single(), uncached.squeeze()[0]);
+    }
+
+    #[test]
+    fn test_squeeze_single_after_state_changes() {
+        let block = [Fr::from(1), Fr::from(2)];
+        let mut cached = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut uncached = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        cached.squeeze_single();
+        uncached.squeeze();
+        cached.absorb_no_permute(&block);
+        uncached.absorb_no_permute(&block);
+        assert_eq!(cached.squeeze_single(), uncached.squeeze()[0]);
+
+        cached.flush();
+        uncached.flush();
+        assert_eq!(cached.squeeze_single(), uncached.squeeze()[0]);
+
+        cached.state_mut()[0] = Fr::from(3);
+        uncached.state_mut()[0] = Fr::from(3);
+        assert_eq!(cached.squeeze_single(), uncached.squeeze()[0]);
+    }
+
+    #[test]
+    fn test_squeeze_final() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)]);
+        sponge.absorb_single(Fr::from(0x10));
+        let output = sponge.squeeze_final();
+        // The partial block is padded and permuted, but the state is not permuted afterwards.
+        assert_eq!(output, [Fr::from(0x02), Fr::from(0x03)]);
+        assert_eq!(sponge.state, [Fr::from(0x02), Fr::from(0x03), Fr::from(0x04), Fr::from(0x11)]);
+    }
+
+    #[derive(MontConfig)]
+    #[modulus = "17"]
+    #[generator = "3"]
+    struct F17Config;
+    type F17 = Fp64<MontBackend<F17Config, 1>>;
+
+    #[test]
+    fn test_squeeze_to_field() {
+        let mut sponge = Sponge::<Fr, SimplePermutation, 2, 4>::new([Fr::from(0x01), Fr::from(0x02), Fr::from(0x03), Fr::from(0x04)]);
+        // Only a single element is needed for the small field, so this is just the reduction
+        // of the first rate element.
+        let challenge: F17 = sponge.squeeze_to_field();
+        assert_eq!(challenge, F17::from(1u64));
+        // Two elements are needed for Fr itself, and the result is f_0 + f_1 * p = f_0.
+        let challenge: Fr = sponge.squeeze_to_field();
+        assert_eq!(challenge, Fr::from(0x02));
+    }
+
+    #[test]
+    fn test_squeeze_to_small_field_has_small_bias() {
+        let mut sponge = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let mut counts = [0usize; 17];
+        for _ in 0..1700 {
+            let challenge: F17 = sponge.squeeze_to_field();
+            counts[challenge.into_bigint().0[0] as usize] += 1;
+        }
+        // Each value is expected to occur 100 times.
+        for count in counts {
+            assert!((50..150).contains(&count), "Unexpected count {}", count);
+        }
+    }
+
+    const _: () = assert!(is_valid_rate(2, 4));
+
+    #[test]
+    fn test_is_valid_rate() {
+        assert!(is_valid_rate(1, 4));
+        assert!(is_valid_rate(3, 4));
+        assert!(!is_valid_rate(0, 4));
+        assert!(!is_valid_rate(4, 4));
+        assert!(!is_valid_rate(5, 4));
+    }
+
+    #[test]
+    fn test_from_state_mut() {
+        let input = [Fr::from(0x01), Fr::from(0x02), Fr::from(0x03)];
+        let mut owned = Sponge::<Fr, PermX5_254_3, 2, 3>::new([Fr::zero(); 3]);
+        let owned_output = owned.hash(&input, 3);
+
+        let mut state = [Fr::zero(); 3];
+        let borrowed_output = Sponge::<Fr, PermX5_254_3, 2, 3, _>::from_state_mut(&mut state).hash(&input, 3);
+        assert_eq!(borrowed_output, owned_output);
+        assert_eq!(state, owned.state);
     }
 }
 
@@ -0,0 +1,115 @@
+//! This file defines a pool of reusable sponges, for servers hashing many independent
+//! requests.
+//!
+//! A `SpongePool` hands out sponges through `get`, which returns a guard dereferencing to the
+//! sponge. When the guard is dropped, the sponge is reset to the initial state of the pool,
+//! and returned to the pool for the next request. The pool can be shared between threads.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use ark_ff::Field;
+
+use crate::sponge::{AbsorbMode, Permutation, Sponge};
+
+/// A pool of sponges with a common initial state, see the module documentation.
+pub struct SpongePool<F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> {
+    start_state: [F; N],
+    mode: AbsorbMode,
+    sponges: Mutex<Vec<Sponge<F, P, RATE, N>>>,
+}
+
+impl<F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> SpongePool<F, P, RATE, N> {
+
+    /// Creates a new, empty pool of sponges with the given initial state.
+    pub fn new(start_state: [F; N]) -> Self {
+        Self::new_with_mode(start_state, AbsorbMode::Add)
+    }
+
+    /// Creates a new, empty pool of sponges with the given initial state, which absorb input
+    /// in the given mode.
+    pub fn new_with_mode(start_state: [F; N], mode: AbsorbMode) -> Self {
+        SpongePool {
+            start_state,
+            mode,
+            sponges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Takes a sponge in its initial state from the pool, or creates a new one if the pool
+    /// is empty. The sponge is returned to the pool when the guard is dropped.
+    pub fn get(&self) -> PooledSponge<'_, F, P, RATE, N> {
+        let sponge = self.sponges.lock().unwrap().pop()
+            .unwrap_or_else(|| Sponge::new_with_mode(self.start_state, self.mode));
+        PooledSponge {
+            pool: self,
+            sponge: Some(sponge),
+        }
+    }
+
+    /// The number of idle sponges in the pool.
+    pub fn idle(&self) -> usize {
+        self.sponges.lock().unwrap().len()
+    }
+}
+
+/// A sponge taken from a `SpongePool`, which is reset and returned to the pool when dropped.
+pub struct PooledSponge<'a, F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> {
+    pool: &'a SpongePool<F, P, RATE, N>,
+    // Only `None` while the sponge is being returned in `drop`.
+    sponge: Option<Sponge<F, P, RATE, N>>,
+}
+
+impl<F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Deref for PooledSponge<'_, F, P, RATE, N> {
+    type Target = Sponge<F, P, RATE, N>;
+
+    fn deref(&self) -> &Self::Target {
+        self.sponge.as_ref().unwrap()
+    }
+}
+
+impl<F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> DerefMut for PooledSponge<'_, F, P, RATE, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.sponge.as_mut().unwrap()
+    }
+}
+
+impl<F: Field, P: Permutation<F, N>, const RATE: usize, const N: usize> Drop for PooledSponge<'_, F, P, RATE, N> {
+    fn drop(&mut self) {
+        if let Some(mut sponge) = self.sponge.take() {
+            sponge.reset(self.pool.start_state);
+            // If the lock is poisoned, the sponge is simply not recycled.
+            if let Ok(mut sponges) = self.pool.sponges.lock() {
+                sponges.push(sponge);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use crate::x5_254_3::{self, PermX5_254_3};
+
+    #[test]
+    fn test_pooled_sponge_is_reset() {
+        let pool = SpongePool::<Fr, PermX5_254_3, 1, 3>::new([Fr::from(0); 3]);
+        let inputs = [vec![Fr::from(1), Fr::from(2)], vec![Fr::from(3)], vec![]];
+        for input in &inputs {
+            let mut sponge = pool.get();
+            assert_eq!(sponge.hash(input, 1), x5_254_3::hash(input).to_vec());
+        }
+        // The same sponge has been recycled for every request.
+        assert_eq!(pool.idle(), 1);
+
+        let mut first = pool.get();
+        let second = pool.get();
+        first.absorb_single(Fr::from(1));
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle(), 2);
+        assert_eq!(pool.get().hash(&[Fr::from(3)], 1), x5_254_3::hash(&[Fr::from(3)]).to_vec());
+    }
+}
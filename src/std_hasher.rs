@@ -0,0 +1,84 @@
+//! This file defines an adapter implementing `core::hash::Hasher` with the x5_254_3 hash, so
+//! that it can be used with `std` collections and other code generic over `Hasher`.
+//!
+//! The written bytes are buffered, and `finish` hashes their length and the bytes packed into
+//! field elements (see `pack_bytes`), and truncates the digest to its 64 least significant
+//! bits. This is a lossy convenience: a 64-bit output offers no meaningful collision
+//! resistance, so it should not be used for cryptographic purposes. It is also much slower
+//! than the hashers usually used for collections.
+
+use std::hash::{BuildHasher, Hasher};
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::transcript::pack_bytes;
+use crate::x5_254_3;
+
+/// A `Hasher` computing the x5_254_3 hash of the written bytes, see the module documentation.
+#[derive(Clone, Debug, Default)]
+pub struct PoseidonHasher {
+    bytes: Vec<u8>,
+}
+
+impl Hasher for PoseidonHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut encoded = vec![Fr::from(self.bytes.len() as u64)];
+        encoded.extend(pack_bytes::<Fr>(&self.bytes));
+        let digest = x5_254_3::hash(&encoded)[0];
+        let bytes = digest.into_bigint().to_bytes_le();
+        u64::from_le_bytes(bytes[..8].try_into().unwrap())
+    }
+}
+
+/// Creates `PoseidonHasher`s, e.g. for `HashMap::with_hasher`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildPoseidonHasher;
+
+impl BuildHasher for BuildPoseidonHasher {
+    type Hasher = PoseidonHasher;
+
+    fn build_hasher(&self) -> PoseidonHasher {
+        PoseidonHasher::default()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_deterministic() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = PoseidonHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b"poseidon"), hash(b"poseidon"));
+        assert_ne!(hash(b"poseidon"), hash(b"poseidonito"));
+        // The length is hashed, so trailing zeroes are not lost in the packing.
+        assert_ne!(hash(b""), hash(&[0]));
+
+        // Writes are buffered, so splitting them does not change the hash.
+        let mut hasher = PoseidonHasher::default();
+        hasher.write(b"posei");
+        hasher.write(b"don");
+        assert_eq!(hasher.finish(), hash(b"poseidon"));
+    }
+
+    #[test]
+    fn test_hash_map() {
+        let mut map = HashMap::with_hasher(BuildPoseidonHasher);
+        map.insert("a", 1);
+        map.insert("b", 2);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get("b"), Some(&2));
+        assert_eq!(BuildPoseidonHasher.hash_one("a"), BuildPoseidonHasher.hash_one("a"));
+    }
+}
@@ -0,0 +1,12 @@
+//! Small prime fields shared by the tests of several modules.
+
+/// The prime field with 101 elements, for testing that no assumptions about the size of the
+/// field are made. Since 5 divides 100, `x^5` is not a permutation of this field, so configs
+/// over it must use another S-box, e.g. `x^3`.
+#[derive(ark_ff::MontConfig)]
+#[modulus = "101"]
+#[generator = "2"]
+pub(crate) struct F101Config;
+pub(crate) type F101 = ark_ff::Fp64<ark_ff::MontBackend<F101Config, 1>>;
+
+crate::impl_field_bytes!(F101);
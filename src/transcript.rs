@@ -0,0 +1,142 @@
+//! This file defines a Fiat-Shamir transcript built on top of the Sponge construction.
+//!
+//! A transcript absorbs a sequence of byte labels (used for domain separation) and field
+//! elements, and produces challenges. In order for two different sequences never to be
+//! absorbed as the same field elements, every input is preceded by a header element
+//! encoding its kind and length:
+//!
+//! - a byte label of length `L` has the header `2 * L + 1`, followed by the bytes packed into
+//!   field elements (see `pack_bytes`),
+//! - a sequence of `k` field elements has the header `2 * k + 2`, followed by the elements.
+//!
+//! Headers are never zero, so that even an empty sequence of field elements changes the
+//! transcript, rather than absorbing a zero which is indistinguishable from padding.
+
+use ark_ff::PrimeField;
+
+use crate::sponge::{Permutation, Sponge};
+
+/// An input to a transcript, which is either a byte label or a sequence of field elements.
+pub enum TranscriptInput<'a, F> {
+    Label(&'a [u8]),
+    FieldElements(&'a [F]),
+}
+
+/// Packs bytes into field elements. The bytes are split into chunks of
+/// `(F::MODULUS_BIT_SIZE - 1) / 8` bytes, and each chunk is read as a little-endian integer.
+/// Since every chunk is smaller than the modulus, no reduction takes place.
+///
+/// Note that the packing does not encode the number of bytes, so it is only unambiguous if
+/// the length is known, e.g. from a header.
+pub fn pack_bytes<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+    let chunk_size = ((F::MODULUS_BIT_SIZE - 1) / 8) as usize;
+    bytes
+        .chunks(chunk_size)
+        .map(F::from_le_bytes_mod_order)
+        .collect()
+}
+
+/// A Fiat-Shamir transcript, parameterized in the same way as the underlying `Sponge`.
+pub struct PoseidonTranscript<F: PrimeField, P: Permutation<F, N>, const RATE: usize, const N: usize> {
+    sponge: Sponge<F, P, RATE, N>,
+}
+
+impl<F: PrimeField, P: Permutation<F, N>, const RATE: usize, const N: usize> PoseidonTranscript<F, P, RATE, N> {
+
+    /// Creates a new transcript, whose sponge has the given initial state.
+    pub fn new(start_state: [F; N]) -> Self {
+        PoseidonTranscript::<F, P, RATE, N> {
+            sponge: Sponge::new(start_state),
+        }
+    }
+
+    /// Absorbs a byte label, preceded by its header.
+    pub fn absorb_label(&mut self, label: &[u8]) {
+        self.sponge.absorb_slice(&[F::from(2 * label.len() as u64 + 1)]);
+        self.sponge.absorb_slice(&pack_bytes::<F>(label));
+    }
+
+    /// Absorbs a sequence of field elements, preceded by its header.
+    pub fn absorb_field_elements(&mut self, elements: &[F]) {
+        self.sponge.absorb_slice(&[F::from(2 * elements.len() as u64 + 2)]);
+        self.sponge.absorb_slice(elements);
+    }
+
+    /// Absorbs a mixed sequence of byte labels and field elements, in order.
+    pub fn absorb_field_and_bytes_mixed(&mut self, inputs: &[TranscriptInput<'_, F>]) {
+        for input in inputs {
+            match input {
+                TranscriptInput::Label(label) => self.absorb_label(label),
+                TranscriptInput::FieldElements(elements) => self.absorb_field_elements(elements),
+            }
+        }
+    }
+
+    /// Squeezes a challenge from the transcript.
+    pub fn challenge(&mut self) -> F {
+        self.sponge.squeeze()[0]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::Zero;
+    use crate::x5_254_3::PermX5_254_3;
+
+    type TestTranscript = PoseidonTranscript<Fr, PermX5_254_3, 2, 3>;
+
+    fn challenge_for(inputs: &[TranscriptInput<'_, Fr>]) -> Fr {
+        let mut transcript = TestTranscript::new([Fr::zero(); 3]);
+        transcript.absorb_field_and_bytes_mixed(inputs);
+        transcript.challenge()
+    }
+
+    #[test]
+    fn test_pack_bytes() {
+        let packed = pack_bytes::<Fr>(&[1u8; 40]);
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[1], Fr::from(0x010101010101010101u128));
+        assert!(pack_bytes::<Fr>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_mixed_matches_separate_calls() {
+        let data = [Fr::from(1), Fr::from(2)];
+        let mut transcript = TestTranscript::new([Fr::zero(); 3]);
+        transcript.absorb_label(b"commitment");
+        transcript.absorb_field_elements(&data);
+        let expected = transcript.challenge();
+        assert_eq!(challenge_for(&[TranscriptInput::Label(b"commitment"), TranscriptInput::FieldElements(&data)]), expected);
+    }
+
+    #[test]
+    fn test_reordering_changes_challenge() {
+        let data = [Fr::from(1), Fr::from(2)];
+        let label_first = challenge_for(&[TranscriptInput::Label(b"commitment"), TranscriptInput::FieldElements(&data)]);
+        let data_first = challenge_for(&[TranscriptInput::FieldElements(&data), TranscriptInput::Label(b"commitment")]);
+        assert_ne!(label_first, data_first);
+    }
+
+    #[test]
+    fn test_encoding_is_unambiguous() {
+        // The label b"\x01" packs to the field element 1, but the headers tell them apart.
+        let label = challenge_for(&[TranscriptInput::Label(b"\x01")]);
+        let element = challenge_for(&[TranscriptInput::FieldElements(&[Fr::from(1)])]);
+        assert_ne!(label, element);
+        // Splitting a sequence of elements changes the encoding.
+        let joined = challenge_for(&[TranscriptInput::FieldElements(&[Fr::from(1), Fr::from(2)])]);
+        let split = challenge_for(&[TranscriptInput::FieldElements(&[Fr::from(1)]), TranscriptInput::FieldElements(&[Fr::from(2)])]);
+        assert_ne!(joined, split);
+    }
+
+    #[test]
+    fn test_empty_field_elements_change_challenge() {
+        let data = [Fr::from(1), Fr::from(2)];
+        let without_empty = challenge_for(&[TranscriptInput::FieldElements(&data)]);
+        let with_empty = challenge_for(&[TranscriptInput::FieldElements(&data), TranscriptInput::FieldElements(&[])]);
+        assert_ne!(without_empty, with_empty);
+    }
+}
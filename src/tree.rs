@@ -0,0 +1,127 @@
+//! This file implements tree hashing with a configurable fan-out, e.g. for computing the
+//! root of a commitment tree.
+//!
+//! Every node with `arity` children is hashed with a single permutation of the configuration
+//! of width `arity + 1`, with the layout of circomlib: the state is `[tag, children...]`, and
+//! the first element of the permuted state is the hash of the node (see `compat`).
+//!
+//! If the number of nodes on a level is not a multiple of the arity, the last group is padded
+//! with zeroes. The tag in the capacity is `padding + level * 2^64 + leaves * 2^128`, where
+//! `padding` is the number of padding elements of the group, `level` is the height of the
+//! node above the leaves (starting at 1 for the parents of the leaves), and `leaves` is the
+//! total number of leaves. This separates padding from children which happen to be zero,
+//! leaves from inner nodes, and trees of different sizes from each other. Since the tag is
+//! never zero, no node is hashed as by circomlib.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInt, PrimeField, Zero};
+
+use crate::configurations::config_x5_254_3::ConfigX5_254_3;
+use crate::configurations::config_x5_254_4::ConfigX5_254_4;
+use crate::configurations::config_x5_254_5::ConfigX5_254_5;
+use crate::configurations::poseidon_config::PoseidonConfig;
+use crate::permutation::perm;
+
+/// The capacity tag of a node, see the module documentation.
+fn node_tag(padding: usize, level: usize, leaves: usize) -> Fr {
+    Fr::from_bigint(BigInt::new([padding as u64, level as u64, leaves as u64, 0])).expect("The tag is below 2^192")
+}
+
+/// Hashes a group of at most `T - 1` nodes on the given level into their parent, see the
+/// module documentation.
+fn hash_group<const T: usize, P: PoseidonConfig<Fr, T>>(group: &[Fr], level: usize, leaves: usize) -> Fr {
+    let mut state = [Fr::zero(); T];
+    state[0] = node_tag(T - 1 - group.len(), level, leaves);
+    state[1..=group.len()].copy_from_slice(group);
+    perm::<Fr, T, P>(&mut state);
+    state[0]
+}
+
+/// Hashes the leaves into the root of a tree with the given fan-out, which must be 2, 3 or
+/// 4, using `ConfigX5_254_3`, `ConfigX5_254_4` and `ConfigX5_254_5` respectively.
+/// 
+/// The levels are hashed until a single node remains, and at least once, so the root of a
+/// single leaf is the hash of the leaf rather than the leaf itself. Panics if there are no
+/// leaves, or if the arity is not supported.
+pub fn hash_tree(leaves: &[Fr], arity: usize) -> Fr {
+    assert!(!leaves.is_empty(), "A tree needs at least one leaf");
+    let hash_group: fn(&[Fr], usize, usize) -> Fr = match arity {
+        2 => hash_group::<3, ConfigX5_254_3>,
+        3 => hash_group::<4, ConfigX5_254_4>,
+        4 => hash_group::<5, ConfigX5_254_5>,
+        _ => panic!("Unsupported arity {}, expected 2, 3 or 4", arity),
+    };
+    let mut nodes = leaves.to_vec();
+    let mut level = 0;
+    while level == 0 || nodes.len() > 1 {
+        level += 1;
+        nodes = nodes.chunks(arity).map(|group| hash_group(group, level, leaves.len())).collect();
+    }
+    nodes[0]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat;
+
+    #[test]
+    fn test_arities_differ() {
+        let leaves: Vec<Fr> = (1..=16u64).map(Fr::from).collect();
+        let binary = hash_tree(&leaves, 2);
+        let quaternary = hash_tree(&leaves, 4);
+        assert_ne!(binary, quaternary);
+        assert_eq!(binary, hash_tree(&leaves, 2));
+    }
+
+    #[test]
+    fn test_tags() {
+        let leaves = crate::field_vec_from_u64(&[1, 2, 3]);
+        let node = |tag: Fr, a: Fr, b: Fr| {
+            let mut state = [tag, a, b];
+            perm::<Fr, 3, ConfigX5_254_3>(&mut state);
+            state[0]
+        };
+        let two_to_128 = Fr::from(1u128 << 64) * Fr::from(1u128 << 64);
+        let tag = |padding: u64, level: u64| Fr::from(padding) + Fr::from(level as u128 * (1u128 << 64)) + Fr::from(3) * two_to_128;
+        let left = node(tag(0, 1), leaves[0], leaves[1]);
+        let right = node(tag(1, 1), leaves[2], Fr::zero());
+        assert_eq!(hash_tree(&leaves, 2), node(tag(0, 2), left, right));
+    }
+
+    #[test]
+    fn test_single_leaf_is_hashed() {
+        let leaf = Fr::from(1);
+        let root = hash_tree(&[leaf], 4);
+        assert_ne!(root, leaf);
+        assert_ne!(root, hash_tree(&[leaf], 2));
+    }
+
+    #[test]
+    fn test_leaves_are_separated_from_nodes() {
+        let leaves = crate::field_vec_from_u64(&[1, 2, 3, 4]);
+        // The inner nodes of a tree with four leaves, with the tags of level 1.
+        let inner = [hash_group::<3, ConfigX5_254_3>(&leaves[..2], 1, 4), hash_group::<3, ConfigX5_254_3>(&leaves[2..], 1, 4)];
+        let root = hash_tree(&leaves, 2);
+        assert_eq!(root, hash_group::<3, ConfigX5_254_3>(&inner, 2, 4));
+        // A tree with the inner nodes as its leaves has another root.
+        assert_ne!(hash_tree(&inner, 2), root);
+        // The circomlib hash of the inner nodes is not the root either.
+        assert_ne!(compat::hash_two(inner[0], inner[1]), root);
+    }
+
+    #[test]
+    fn test_padding_is_domain_separated() {
+        let leaves = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let padded = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::zero()];
+        assert_ne!(hash_tree(&leaves, 4), hash_tree(&padded, 4));
+        assert_ne!(hash_tree(&leaves, 2), hash_tree(&padded, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsupported_arity() {
+        hash_tree(&[Fr::from(1), Fr::from(2)], 5);
+    }
+}
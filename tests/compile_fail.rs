@@ -0,0 +1,13 @@
+//! Checks that misconfigurations which are rejected at compile time indeed fail to compile,
+//! while the corresponding valid configurations compile.
+//!
+//! The checks are evaluated after monomorphization, which `cargo check` skips. Since trybuild
+//! builds the test cases (rather than only checking them) when there are passing cases, the
+//! valid configurations are needed for the failing ones to be detected.
+
+#[test]
+fn test_compile_fail() {
+    let tests = trybuild::TestCases::new();
+    tests.pass("tests/ui/pass/*.rs");
+    tests.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,127 @@
+//! Interoperability tests against the `light-poseidon` crate, which implements the circom
+//! flavour of Poseidon over BN254.
+//!
+//! The parameters are aligned as follows:
+//!
+//! - field: the BN254 scalar field,
+//! - state width: t = 3, i.e. `Poseidon::<Fr>::new_circom(2)`,
+//! - rounds: R_F = 8 full rounds and R_P = 57 partial rounds, with the S-box `x^5`
+//!   (for t = 2, 4, 5 and 6, as in `ConfigX5_254_2`, `ConfigX5_254_4`, `ConfigX5_254_5` and
+//!   `ConfigX5_254_6`, R_P = 56, 56, 60 and 60),
+//! - round constants and MDS matrix: both libraries use the Grain-generated parameters of the
+//!   reference implementation, so `ConfigX5_254_3` and light-poseidon's t = 3 parameters are
+//!   identical, and the partial S-box is applied to `state[0]` in both.
+//!
+//! The libraries differ in how the permutation is turned into a hash. light-poseidon hashes
+//! exactly `t - 1` inputs with a single permutation of `[0, a, b]`, i.e. with the capacity
+//! (the domain tag, zero by default) in `state[0]` and the inputs in the rate, and outputs
+//! `state[0]`. `x5_254_3::hash` is a rate-1 sponge with the rate in `state[0]` and a zero
+//! initial state, so its digests intentionally differ from light-poseidon's. The tests below
+//! therefore compare the permutations directly, and check that `x5_254_3::hash` is the sponge
+//! built on top of that permutation.
+
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use light_poseidon::{Poseidon, PoseidonHasher};
+use poseidonito::ConfigX5_254_3;
+use poseidonito::perm;
+use poseidonito::x5_254_3;
+
+fn circom_hash(inputs: &[Fr; 2]) -> Fr {
+    let mut state = [Fr::zero(), inputs[0], inputs[1]];
+    perm::<Fr, 3, ConfigX5_254_3>(&mut state);
+    state[0]
+}
+
+fn input_pairs() -> Vec<[Fr; 2]> {
+    vec![
+        [Fr::from(0), Fr::from(0)],
+        [Fr::from(1), Fr::from(2)],
+        [Fr::from(u64::MAX), Fr::from(3)],
+        [-Fr::from(1), -Fr::from(2)],
+        [Fr::from(123456789u64), Fr::from(987654321u64)],
+    ]
+}
+
+#[test]
+fn test_permutation_matches_light_poseidon() {
+    let mut hasher = Poseidon::<Fr>::new_circom(2).unwrap();
+    for inputs in input_pairs() {
+        assert_eq!(circom_hash(&inputs), hasher.hash(&inputs).unwrap());
+        assert_eq!(x5_254_3::semaphore_identity_commitment(inputs[0], inputs[1]), hasher.hash(&inputs).unwrap());
+    }
+}
+
+#[test]
+fn test_circomlib_vector() {
+    // poseidon([1, 2]) from the circomlib test vectors, checked against both libraries.
+    let expected = ark_ff::MontFp!("7853200120776062878684798364095072458815029376092732009249414926327459813530");
+    let mut hasher = Poseidon::<Fr>::new_circom(2).unwrap();
+    assert_eq!(hasher.hash(&[Fr::from(1), Fr::from(2)]).unwrap(), expected);
+    assert_eq!(circom_hash(&[Fr::from(1), Fr::from(2)]), expected);
+}
+
+#[test]
+fn test_hash_is_sponge_over_shared_permutation() {
+    // With rate 1, hashing [a, b] absorbs a, permutes, absorbs b, permutes and reads state[0].
+    for inputs in input_pairs() {
+        let mut state = [inputs[0], Fr::zero(), Fr::zero()];
+        perm::<Fr, 3, ConfigX5_254_3>(&mut state);
+        state[0] += inputs[1];
+        perm::<Fr, 3, ConfigX5_254_3>(&mut state);
+        assert_eq!(x5_254_3::hash(&inputs), [state[0]]);
+    }
+}
+
+#[test]
+fn test_t4_matches_light_poseidon() {
+    use poseidonito::ConfigX5_254_4;
+    let mut hasher = Poseidon::<Fr>::new_circom(3).unwrap();
+    let inputs = [Fr::from(1), Fr::from(2), Fr::from(3)];
+    let mut state = [Fr::zero(), inputs[0], inputs[1], inputs[2]];
+    perm::<Fr, 4, ConfigX5_254_4>(&mut state);
+    assert_eq!(state[0], hasher.hash(&inputs).unwrap());
+    assert_eq!(poseidonito::compat::hash_three(inputs[0], inputs[1], inputs[2]), hasher.hash(&inputs).unwrap());
+}
+
+#[test]
+fn test_t5_matches_light_poseidon() {
+    use poseidonito::ConfigX5_254_5;
+    let mut hasher = Poseidon::<Fr>::new_circom(4).unwrap();
+    let inputs = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4)];
+    let mut state = [Fr::zero(), inputs[0], inputs[1], inputs[2], inputs[3]];
+    perm::<Fr, 5, ConfigX5_254_5>(&mut state);
+    assert_eq!(state[0], hasher.hash(&inputs).unwrap());
+}
+
+#[test]
+fn test_t6_matches_light_poseidon() {
+    use poseidonito::ConfigX5_254_6;
+    let mut hasher = Poseidon::<Fr>::new_circom(5).unwrap();
+    let inputs = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4), Fr::from(5)];
+    let mut state = [Fr::zero(), inputs[0], inputs[1], inputs[2], inputs[3], inputs[4]];
+    perm::<Fr, 6, ConfigX5_254_6>(&mut state);
+    assert_eq!(state[0], hasher.hash(&inputs).unwrap());
+}
+
+#[test]
+fn test_x5_254_6_hash_matches_light_poseidon() {
+    // `x5_254_6::hash` of five elements permutes `[a, b, c, d, e, 5]`, with the length in the
+    // capacity, which is light-poseidon's state for the domain tag `a` and the inputs
+    // `[b, c, d, e, 5]`.
+    let inputs = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4), Fr::from(5)];
+    let mut hasher = Poseidon::<Fr>::with_domain_tag_circom(5, inputs[0]).unwrap();
+    let expected = hasher.hash(&[inputs[1], inputs[2], inputs[3], inputs[4], Fr::from(5)]).unwrap();
+    assert_eq!(poseidonito::x5_254_6::hash(&inputs)[0], expected);
+    let mut circom = Poseidon::<Fr>::new_circom(5).unwrap();
+    assert_ne!(poseidonito::x5_254_6::hash(&inputs)[0], circom.hash(&inputs).unwrap());
+}
+
+#[test]
+fn test_circom_compatible_matches_light_poseidon() {
+    for n in 1..=5u64 {
+        let inputs: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        let mut hasher = Poseidon::<Fr>::new_circom(n as usize).unwrap();
+        assert_eq!(poseidonito::compat::hash_circom_compatible(&inputs), hasher.hash(&inputs).unwrap());
+    }
+}
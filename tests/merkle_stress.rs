@@ -0,0 +1,70 @@
+//! A stress test of the `ark-crh` compressors in arkworks' Merkle tree, with 2^16 leaves.
+//!
+//! The test is ignored by default, since building the tree takes a while (in particular in
+//! debug builds). Run it with
+//! `cargo test --release --features ark-crh --test merkle_stress -- --ignored --nocapture`.
+#![cfg(feature = "ark-crh")]
+
+use std::time::Instant;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::merkle_tree::{Config, IdentityDigestConverter, MerkleTree};
+use ark_std::rand::Rng;
+use ark_std::{test_rng, UniformRand};
+use poseidonito::ark_crh::{PoseidonCRH, PoseidonTwoToOneCRH};
+use poseidonito::x5_254_3;
+
+const LEAVES: usize = 1 << 16;
+const PROOFS: usize = 256;
+const UPDATES: usize = 64;
+
+struct PoseidonMerkleConfig;
+impl Config for PoseidonMerkleConfig {
+    type Leaf = [Fr];
+    type LeafDigest = Fr;
+    type LeafInnerDigestConverter = IdentityDigestConverter<Fr>;
+    type InnerDigest = Fr;
+    type LeafHash = PoseidonCRH;
+    type TwoToOneHash = PoseidonTwoToOneCRH;
+}
+
+/// Computes the root directly, by hashing the leaves and then folding the levels pairwise.
+fn full_root(leaves: &[[Fr; 1]]) -> Fr {
+    let mut level: Vec<Fr> = leaves.iter().map(|leaf| x5_254_3::hash(leaf)[0]).collect();
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| x5_254_3::compress_two(pair[0], pair[1])).collect();
+    }
+    level[0]
+}
+
+#[test]
+#[ignore]
+fn test_merkle_tree_stress() {
+    let mut rng = test_rng();
+    let mut leaves: Vec<[Fr; 1]> = (0..LEAVES).map(|_| [Fr::rand(&mut rng)]).collect();
+
+    let start = Instant::now();
+    let mut tree = MerkleTree::<PoseidonMerkleConfig>::new(&(), &(), &leaves).unwrap();
+    println!("Built a tree of {} leaves in {:?}", LEAVES, start.elapsed());
+    assert_eq!(tree.root(), full_root(&leaves));
+
+    for _ in 0..PROOFS {
+        let index = rng.gen_range(0..LEAVES);
+        let proof = tree.generate_proof(index).unwrap();
+        assert!(proof.verify(&(), &(), &tree.root(), leaves[index]).unwrap());
+        let wrong_leaf = leaves[(index + 1) % LEAVES];
+        assert!(!proof.verify(&(), &(), &tree.root(), wrong_leaf).unwrap());
+    }
+
+    // The incrementally updated tree must agree with a full rebuild.
+    let start = Instant::now();
+    for _ in 0..UPDATES {
+        let index = rng.gen_range(0..LEAVES);
+        leaves[index] = [Fr::rand(&mut rng)];
+        tree.update(index, &leaves[index]).unwrap();
+    }
+    println!("Applied {} updates in {:?}", UPDATES, start.elapsed());
+    assert_eq!(tree.root(), full_root(&leaves));
+    let proof = tree.generate_proof(0).unwrap();
+    assert!(proof.verify(&(), &(), &tree.root(), leaves[0]).unwrap());
+}
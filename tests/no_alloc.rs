@@ -0,0 +1,54 @@
+//! Checks that `x5_254_3::hash_fixed_noalloc` runs without touching the heap, as required for
+//! targets without an allocator. This is a separate test binary, since it installs a global
+//! allocator which counts the allocations made by the current thread while counting is on.
+//!
+//! This is not a `no_std` test harness: the crate depends on `std` (e.g. for its lazily
+//! parsed parameters and the `Vec`-returning APIs), so it cannot be built for a `no_std`
+//! target, and a `#![no_std]` check is out of scope until that dependency is made optional.
+//! The test only checks the allocation-free property that such targets need.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use ark_bn254::Fr;
+use poseidonito::x5_254_3;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made by `f` on the current thread.
+fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    ALLOCATIONS.with(|allocations| allocations.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    let result = f();
+    COUNTING.with(|counting| counting.set(false));
+    (result, ALLOCATIONS.with(Cell::get))
+}
+
+#[test]
+fn test_hash_fixed_noalloc_does_not_allocate() {
+    let input = [Fr::from(1), Fr::from(2), Fr::from(3)];
+    let (digest, allocations) = count_allocations(|| x5_254_3::hash_fixed_noalloc(&input));
+    assert_eq!(allocations, 0);
+    assert_eq!(digest, x5_254_3::hash(&input)[0]);
+}
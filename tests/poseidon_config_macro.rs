@@ -0,0 +1,33 @@
+//! Tests of the `poseidon_config` attribute, by generating the x5_254_3 configuration from
+//! its parameter files and comparing it with `ConfigX5_254_3`.
+
+use ark_bn254::Fr;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::PoseidonConfig;
+use poseidonito::perm;
+use poseidonito::poseidon_config;
+
+#[poseidon_config(
+    field = "bn254",
+    t = 3,
+    r_f = 8,
+    r_p = 57,
+    constants = "../src/configurations/config_x5_254_3/constants.txt",
+    matrix = "data/x5_254_3_matrix.txt"
+)]
+struct GeneratedX5_254_3;
+
+#[test]
+fn test_generated_config_matches_x5_254_3() {
+    assert_eq!(GeneratedX5_254_3::R_F, ConfigX5_254_3::R_F);
+    assert_eq!(GeneratedX5_254_3::R_P, ConfigX5_254_3::R_P);
+    assert_eq!(GeneratedX5_254_3::ALPHA, ConfigX5_254_3::ALPHA);
+    assert_eq!(GeneratedX5_254_3::mds_matrix(), ConfigX5_254_3::mds_matrix());
+    assert_eq!(GeneratedX5_254_3::round_constants(), ConfigX5_254_3::round_constants());
+
+    let mut generated = [Fr::from(0), Fr::from(1), Fr::from(2)];
+    perm::<Fr, 3, GeneratedX5_254_3>(&mut generated);
+    let mut expected = [Fr::from(0), Fr::from(1), Fr::from(2)];
+    perm::<Fr, 3, ConfigX5_254_3>(&mut expected);
+    assert_eq!(generated, expected);
+}
@@ -0,0 +1,8 @@
+use ark_bn254::Fr;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::poseidon_hash;
+
+fn main() {
+    // A rate equal to the state size leaves no capacity, which is rejected at compile time.
+    poseidon_hash::<Fr, ConfigX5_254_3, 3, 3>(&[Fr::from(1)]);
+}
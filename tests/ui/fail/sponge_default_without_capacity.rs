@@ -0,0 +1,12 @@
+use ark_bn254::Fr;
+use poseidonito::sponge::{Permutation, Sponge};
+
+struct IdentityPermutation;
+impl Permutation<Fr, 3> for IdentityPermutation {
+    fn apply(_state: &mut [Fr; 3]) {}
+}
+
+fn main() {
+    // The default sponge needs a capacity element for its rate tag.
+    let _sponge = Sponge::<Fr, IdentityPermutation, 3, 3>::default();
+}
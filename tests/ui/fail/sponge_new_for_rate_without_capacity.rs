@@ -0,0 +1,12 @@
+use ark_bn254::Fr;
+use poseidonito::sponge::{Permutation, Sponge};
+
+struct IdentityPermutation;
+impl Permutation<Fr, 4> for IdentityPermutation {
+    fn apply(_state: &mut [Fr; 4]) {}
+}
+
+fn main() {
+    // A rate equal to the state size leaves no capacity for the rate tag.
+    let _sponge = Sponge::<Fr, IdentityPermutation, 4, 4>::new_for_rate();
+}
@@ -0,0 +1,13 @@
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use poseidonito::sponge::{Permutation, Sponge};
+
+struct IdentityPermutation;
+impl Permutation<Fr, 4> for IdentityPermutation {
+    fn apply(_state: &mut [Fr; 4]) {}
+}
+
+fn main() {
+    // A rate larger than the state size is rejected at compile time.
+    let _sponge = Sponge::<Fr, IdentityPermutation, 5, 4>::new([Fr::zero(); 4]);
+}
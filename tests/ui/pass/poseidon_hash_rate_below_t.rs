@@ -0,0 +1,7 @@
+use ark_bn254::Fr;
+use poseidonito::ConfigX5_254_3;
+use poseidonito::poseidon_hash;
+
+fn main() {
+    poseidon_hash::<Fr, ConfigX5_254_3, 2, 3>(&[Fr::from(1)]);
+}
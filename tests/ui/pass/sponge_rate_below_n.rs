@@ -0,0 +1,14 @@
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use poseidonito::sponge::{Permutation, Sponge};
+
+struct IdentityPermutation;
+impl Permutation<Fr, 4> for IdentityPermutation {
+    fn apply(_state: &mut [Fr; 4]) {}
+}
+
+fn main() {
+    let _sponge = Sponge::<Fr, IdentityPermutation, 3, 4>::new([Fr::zero(); 4]);
+    let _sponge = Sponge::<Fr, IdentityPermutation, 1, 4>::new_for_rate();
+    let _sponge = Sponge::<Fr, IdentityPermutation, 2, 4>::default();
+}